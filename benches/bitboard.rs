@@ -0,0 +1,43 @@
+//! Evolves the same 1024 x 1024 soup with `Community` and `Bitboard`, and
+//! reports how much faster the bit-packed backend is. Timings depend on the
+//! machine and its load, so nothing is asserted.
+//!
+//! Run with `cargo bench --bench bitboard`.
+
+extern crate conway;
+
+use std::time::Instant;
+
+use conway::bitboard::Bitboard;
+use conway::community::Community;
+use conway::grid::Grid;
+
+
+const SIZE: usize = 1024;
+
+
+/// Average seconds per generation.
+fn time<G: Grid>(mut grid: G, generations: usize) -> f64 {
+    let start = Instant::now();
+
+    for _ in 0..generations {
+        grid = grid.evolve();
+    }
+
+    let elapsed = start.elapsed();
+
+    (elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9) / generations as f64
+}
+
+
+fn main() {
+    let community = Community::seeded(SIZE, 0.5, 1);
+    let bitboard = Bitboard::from_community(&community).unwrap();
+
+    let slow = time(community, 5);
+    let fast = time(bitboard, 100);
+
+    println!("community: {:.3} ms per generation", slow * 1e3);
+    println!("bitboard:  {:.3} ms per generation", fast * 1e3);
+    println!("speedup:   {:.1}x", slow / fast);
+}
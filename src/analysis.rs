@@ -0,0 +1,269 @@
+//! Pattern analysis over grid snapshots.
+//!
+//! A `Shape` is the set of alive cells of a grid moved to the top left corner
+//! of their bounding box. Comparing shapes across generations finds patterns
+//! that come back to themselves, possibly somewhere else: oscillators and
+//! spaceships.
+
+use std::fmt;
+
+use coord::Point;
+use grid::{ Grid, GridItem };
+
+
+/// Alive cells relative to their bounding box, together with where the box
+/// was found.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shape {
+    cells: Vec<Point>,
+    origin: Point,
+}
+
+impl Shape {
+    /// The shape of the given cells, `None` if there are none.
+    pub fn new<I: IntoIterator<Item = Point>>(points: I) -> Option<Self> {
+        let mut cells: Vec<Point> = points.into_iter().collect();
+
+        let origin = match cells.first() {
+            Some(&first) => {
+                cells.iter().fold(first, |min, c| Point(min.0.min(c.0), min.1.min(c.1)))
+            }
+            None => return None,
+        };
+
+        for cell in &mut cells {
+            *cell = cell.offset(-origin.0, -origin.1);
+        }
+
+        cells.sort();
+        cells.dedup();
+
+        Some(Shape {
+            cells: cells,
+            origin: origin,
+        })
+    }
+
+    /// The shape of the alive cells of the grid.
+    pub fn of<G, C>(grid: G) -> Option<Self>
+        where G: Grid + IntoIterator<Item = (C, <G as Grid>::Cell)>,
+              C: Into<Point> {
+        Shape::new(grid.into_iter()
+                       .filter(|&(_, ref cell)| cell.is_alive())
+                       .map(|(coord, _)| coord.into()))
+    }
+
+    /// Cells relative to the top left corner, sorted.
+    pub fn cells(&self) -> &[Point] {
+        &self.cells
+    }
+
+    /// The top left corner of the bounding box in the grid.
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+
+    /// Whether both shapes are the same, wherever they are.
+    pub fn congruent(&self, other: &Shape) -> bool {
+        self.cells == other.cells
+    }
+
+    /// The shape in each of its 8 rotations and reflections, starting with
+    /// itself. The origin is kept.
+    pub fn orientations(&self) -> Vec<Shape> {
+        let symmetries: [fn(Point) -> Point; 8] = [
+            |p| Point(p.0, p.1),
+            |p| Point(p.0, -p.1),
+            |p| Point(-p.0, p.1),
+            |p| Point(-p.0, -p.1),
+            |p| Point(p.1, p.0),
+            |p| Point(p.1, -p.0),
+            |p| Point(-p.1, p.0),
+            |p| Point(-p.1, -p.0),
+        ];
+
+        symmetries.iter()
+            .filter_map(|f| Shape::new(self.cells.iter().map(|&p| f(p))))
+            .map(|x| Shape { origin: self.origin, .. x })
+            .collect()
+    }
+
+    /// The smallest of its orientations, so every orientation of an object
+    /// gives the same canonical shape.
+    pub fn canonical(&self) -> Shape {
+        self.orientations()
+            .into_iter()
+            .min_by(|a, b| a.cells.cmp(&b.cells))
+            .unwrap_or_else(|| self.clone())
+    }
+}
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Orthogonal,
+    Diagonal,
+    Oblique,
+}
+
+
+/// A pattern that comes back to its own shape after `period` generations,
+/// moved `dx` rows and `dy` columns.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub period: usize,
+    pub dx: isize,
+    pub dy: isize,
+}
+
+impl Recurrence {
+    pub fn is_spaceship(&self) -> bool {
+        self.dx != 0 || self.dy != 0
+    }
+
+    /// `None` for patterns that stay in place.
+    pub fn direction(&self) -> Option<Direction> {
+        let (dx, dy) = (self.dx.abs(), self.dy.abs());
+
+        if !self.is_spaceship() {
+            None
+        } else if dx == 0 || dy == 0 {
+            Some(Direction::Orthogonal)
+        } else if dx == dy {
+            Some(Direction::Diagonal)
+        } else {
+            Some(Direction::Oblique)
+        }
+    }
+
+    /// The speed as cells per generation, reduced. E.g. `(1, 2)` is `c/2`.
+    pub fn speed(&self) -> (usize, usize) {
+        let cells = self.dx.abs().max(self.dy.abs()) as usize;
+        let n = gcd(cells, self.period);
+
+        (cells / n, self.period / n)
+    }
+}
+
+/// Written the usual way: `c/4` for the glider, `2c/5` or `(2,1)c/6` for an
+/// oblique spaceship.
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.direction(), self.speed()) {
+            (None, _) => write!(f, "p{}", self.period),
+            (Some(Direction::Oblique), _) => {
+                let (dx, dy) = (self.dx.abs(), self.dy.abs());
+                write!(f, "({},{})c/{}", dx.max(dy), dx.min(dy), self.period)
+            }
+            (_, (1, p)) => write!(f, "c/{}", p),
+            (_, (c, p)) => write!(f, "{}c/{}", c, p),
+        }
+    }
+}
+
+
+/// Evolves the grid up to `max_period` generations looking for its shape. The
+/// grid must be large enough for the pattern not to wrap around or hit a
+/// border.
+pub fn recurrence<G, C>(grid: &G, max_period: usize) -> Option<Recurrence>
+    where G: Grid + IntoIterator<Item = (C, <G as Grid>::Cell)>,
+          C: Into<Point> {
+    let start = match Shape::of(grid.clone()) {
+        Some(x) => x,
+        None => return None,
+    };
+
+    let mut grid = grid.clone();
+
+    for period in 1..max_period + 1 {
+        grid = grid.evolve();
+
+        let shape = match Shape::of(grid.clone()) {
+            Some(x) => x,
+            None => return None,
+        };
+
+        if shape.congruent(&start) {
+            return Some(Recurrence {
+                period: period,
+                dx: shape.origin.0 - start.origin.0,
+                dy: shape.origin.1 - start.origin.1,
+            });
+        }
+    }
+
+    None
+}
+
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+
+#[test]
+fn test_glider() {
+    use colony::Colony;
+    use pattern::{ Glider, Layout };
+
+    let mut grid = Colony::empty();
+    grid.insert(Layout::new((0, 0), Glider::BottomRight));
+
+    let x = recurrence(&grid, 10).unwrap();
+
+    assert_eq!(x, Recurrence { period: 4, dx: 1, dy: 1 });
+    assert_eq!(x.direction(), Some(Direction::Diagonal));
+    assert_eq!(x.to_string(), "c/4");
+}
+
+#[test]
+fn test_lightweight_spaceship() {
+    use colony::Colony;
+    use pattern::{ Layout, LightweightSpaceship };
+
+    let mut grid = Colony::empty();
+    grid.insert(Layout::new((0, 0), LightweightSpaceship::Right));
+
+    let x = recurrence(&grid, 10).unwrap();
+
+    assert_eq!((x.period, x.dx, x.dy.abs()), (4, 0, 2));
+    assert_eq!(x.direction(), Some(Direction::Orthogonal));
+    assert_eq!(x.speed(), (1, 2));
+    assert_eq!(x.to_string(), "c/2");
+}
+
+#[test]
+fn test_oscillator() {
+    use community::Community;
+    use pattern::{ Layout, Pulsar };
+
+    let mut grid = Community::empty(20);
+    grid.insert(Layout::new((3, 3), Pulsar));
+
+    let x = recurrence(&grid, 10).unwrap();
+
+    assert!(!x.is_spaceship());
+    assert_eq!(x.period, 3);
+    assert_eq!(x.to_string(), "p3");
+    assert_eq!(Recurrence { period: 6, dx: 1, dy: -2 }.to_string(), "(2,1)c/6");
+    assert_eq!(Recurrence { period: 5, dx: 2, dy: 0 }.to_string(), "2c/5");
+}
+
+#[test]
+fn test_canonical() {
+    use community::Community;
+    use pattern::{ Glider, Layout };
+
+    let gliders = vec![Glider::BottomLeft, Glider::BottomRight, Glider::TopLeft, Glider::TopRight];
+    let shapes: Vec<Shape> = gliders.into_iter()
+        .map(|x| {
+            let mut grid = Community::empty(8);
+            grid.insert(Layout::new((2, 3), x));
+            Shape::of(grid).unwrap()
+        })
+        .collect();
+
+    assert!(!shapes[0].congruent(&shapes[1]));
+    assert!(shapes.iter().all(|x| x.canonical().congruent(&shapes[0].canonical())));
+    assert_eq!(shapes[0].canonical().origin(), Point(2, 3));
+}
@@ -0,0 +1,250 @@
+//! Canonical pattern codes, as used by apgsearch and Catagolue.
+//!
+//! An apgcode is a prefix telling what the object is, `xs` for still lifes
+//! followed by the population, `xp` for oscillators and `xq` for spaceships
+//! followed by the period, and the extended Wechsler format of its cells:
+//!
+//! * The pattern is cut in strips of 5 rows, separated by `z`.
+//! * Each column of a strip is a digit from `0` to `v`, with the top row in
+//!   the lowest bit. Zeros at the end of a strip are dropped.
+//! * Runs of zeros are `w` (2), `x` (3) and `y` followed by a digit from `0`
+//!   to `z` (4 to 39).
+//!
+//! The code chosen is the shortest, then smallest, of every orientation and
+//! phase, so it doesn't depend on where or how the object was found.
+
+use std::cmp::Ordering;
+use ndarray::prelude::*;
+
+use analysis::{ self, Shape };
+use cell::Cell;
+use colony::Colony;
+use coord::Point;
+use grid::Grid;
+use pattern::{ Custom, Layout, Pattern };
+
+
+const DIGITS: &'static [u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Longest period looked for.
+pub const MAX_PERIOD: usize = 1000;
+
+
+/// The apgcode of the object made of every alive cell of the grid, `None`
+/// if it dies out or doesn't repeat itself within `MAX_PERIOD`
+/// generations.
+pub fn encode<G, C>(grid: &G) -> Option<String>
+    where G: Grid + IntoIterator<Item = (C, <G as Grid>::Cell)>,
+          C: Into<Point> {
+    let recurrence = match analysis::recurrence(grid, MAX_PERIOD) {
+        Some(x) => x,
+        None => return None,
+    };
+
+    let mut grid = grid.clone();
+    let mut best: Option<String> = None;
+    let mut population = 0;
+
+    for _ in 0..recurrence.period {
+        if let Some(shape) = Shape::of(grid.clone()) {
+            population = shape.cells().len();
+
+            for x in shape.orientations() {
+                let code = wechsler(&x);
+
+                if best.as_ref().map_or(true, |y| compare(&code, y) == Ordering::Less) {
+                    best = Some(code);
+                }
+            }
+        }
+
+        grid = grid.evolve();
+    }
+
+    let prefix = if recurrence.is_spaceship() {
+        format!("xq{}", recurrence.period)
+    } else if recurrence.period == 1 {
+        format!("xs{}", population)
+    } else {
+        format!("xp{}", recurrence.period)
+    };
+
+    best.map(|x| format!("{}_{}", prefix, x))
+}
+
+/// The apgcode of a pattern, e.g. `xq4_153` for `Glider`.
+pub fn of<T: Pattern<Array2<Cell>>>(pattern: T) -> Option<String> {
+    let mut grid = Colony::empty();
+    grid.insert(Layout::new((0, 0), pattern));
+
+    encode(&grid)
+}
+
+
+/// The cells of the code, in the orientation and phase it describes.
+pub fn decode(code: &str) -> Result<Custom, String> {
+    let (prefix, body) = match code.find('_') {
+        Some(i) => (&code[..i], &code[i + 1..]),
+        None => return Err(format!("Missing apgcode prefix in '{}'", code)),
+    };
+
+    let valid = ["xs", "xp", "xq"].iter().any(|x| prefix.starts_with(x)) &&
+                prefix.len() > 2 && prefix[2..].chars().all(|c| c.is_digit(10));
+
+    if !valid {
+        return Err(format!("Invalid apgcode prefix '{}'", prefix));
+    }
+
+    let mut points = vec![];
+    let mut chars = body.chars();
+    let (mut strip, mut column) = (0, 0);
+
+    while let Some(c) = chars.next() {
+        match c {
+            'z' => {
+                strip += 5;
+                column = 0;
+            }
+            'w' => column += 2,
+            'x' => column += 3,
+            'y' => {
+                let n = chars.next().and_then(digit).ok_or("Missing run length after 'y'")?;
+                column += 4 + n;
+            }
+            c => {
+                let bits = digit(c).ok_or_else(|| format!("Unexpected '{}' in apgcode", c))?;
+
+                for row in 0..5 {
+                    if bits & (1 << row) != 0 {
+                        points.push(Point((strip + row) as isize, column as isize));
+                    }
+                }
+
+                column += 1;
+            }
+        }
+    }
+
+    let rows = points.iter().map(|x| x.0 + 1).max().unwrap_or(0) as usize;
+    let cols = points.iter().map(|x| x.1 + 1).max().unwrap_or(0) as usize;
+    let mut cells = Array2::from_elem((rows, cols), Cell::Unborn);
+
+    for point in points {
+        cells[[point.0 as usize, point.1 as usize]] = Cell::Alive;
+    }
+
+    Ok(Custom(cells))
+}
+
+
+/// The extended Wechsler format of the shape as it is.
+pub fn wechsler(shape: &Shape) -> String {
+    let rows = shape.cells().iter().map(|x| x.0 + 1).max().unwrap_or(0) as usize;
+    let cols = shape.cells().iter().map(|x| x.1 + 1).max().unwrap_or(0) as usize;
+    let mut strips = vec![vec![0; cols]; (rows + 4) / 5];
+
+    for point in shape.cells() {
+        let (x, y) = (point.0 as usize, point.1 as usize);
+        strips[x / 5][y] |= 1 << (x % 5);
+    }
+
+    strips.into_iter()
+        .map(|strip| {
+            let mut res = String::new();
+            let mut zeros = 0;
+
+            for bits in strip {
+                if bits == 0 {
+                    zeros += 1;
+                } else {
+                    res.push_str(&run(zeros));
+                    res.push(DIGITS[bits] as char);
+                    zeros = 0;
+                }
+            }
+
+            res
+        })
+        .collect::<Vec<String>>()
+        .join("z")
+}
+
+
+/// Shortest first, then in alphabetical order.
+fn compare(a: &str, b: &str) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+fn digit(c: char) -> Option<usize> {
+    DIGITS.iter().position(|&x| x as char == c)
+}
+
+/// `n` zeros.
+fn run(mut n: usize) -> String {
+    let mut res = String::new();
+
+    while n > 0 {
+        let k = n.min(39);
+
+        match k {
+            1 => res.push('0'),
+            2 => res.push('w'),
+            3 => res.push('x'),
+            _ => {
+                res.push('y');
+                res.push(DIGITS[k - 4] as char);
+            }
+        }
+
+        n -= k;
+    }
+
+    res
+}
+
+
+#[test]
+fn test_encode() {
+    use pattern::*;
+
+    assert_eq!(of(Block), Some("xs4_33".into()));
+    assert_eq!(of(Beehive), Some("xs6_696".into()));
+    assert_eq!(of(Blinker), Some("xp2_7".into()));
+    assert_eq!(of(Glider::TopLeft), Some("xq4_153".into()));
+    assert_eq!(of(LightweightSpaceship::Bottom), Some("xq4_6frc".into()));
+    assert_eq!(of(Pulsar), Some("xp3_co9nas0san9oczgoldlo0oldlogz1047210127401".into()));
+}
+
+#[test]
+fn test_decode() {
+    use pattern::Glider;
+
+    let glider = decode("xq4_153").unwrap();
+
+    assert_eq!(glider.0.dim(), (3, 3));
+    assert_eq!(of(glider), of(Glider::BottomRight));
+    assert_eq!(decode("xs8_33y133").unwrap().0.dim(), (2, 9));
+    assert_eq!(decode("xs8_3zw3").unwrap().0.dim(), (7, 3));
+    assert!(decode("xs4_3!3").is_err());
+    assert!(decode("xs4_3y").is_err());
+    assert!(decode("ov_s2").is_err());
+}
+
+#[test]
+fn test_wechsler() {
+    use grid::GridItem;
+
+    let shape = |xs: &[(isize, isize)]| Shape::new(xs.iter().map(|&(x, y)| Point(x, y))).unwrap();
+
+    assert_eq!(wechsler(&shape(&[(0, 0), (0, 45)])), "1yzy11");
+    assert_eq!(wechsler(&shape(&[(0, 0), (6, 3)])), "1zx2");
+
+    for code in vec!["xs8_33y133", "xs8_3zw3", "xp2_7"] {
+        let cells = decode(code).unwrap().0;
+        let points = cells.indexed_iter()
+            .filter(|&(_, cell)| cell.is_alive())
+            .map(|((x, y), _)| Point(x as isize, y as isize));
+
+        assert_eq!(wechsler(&Shape::new(points).unwrap()), &code[code.find('_').unwrap() + 1..]);
+    }
+}
@@ -0,0 +1,198 @@
+//! Grid of multi-state cells evolving with a rule table, see `table`.
+
+use itertools::Itertools;
+use ndarray::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use cell::State;
+use coord::Dim2 as Coord;
+use grid::Grid;
+use table::RuleTable;
+use topology::Topology;
+
+
+#[derive(Debug, Clone, Eq)]
+pub struct Automaton {
+    cells: Array2<State>,
+    size: (usize, usize),
+    gen: usize,
+    table: Arc<RuleTable>,
+    topology: Topology,
+}
+
+impl Automaton {
+    /// An empty grid of the given size.
+    pub fn new(table: RuleTable, rows: usize, cols: usize) -> Self {
+        Automaton {
+            cells: Array2::from_elem((rows, cols), State(0)),
+            size: (rows, cols),
+            gen: 1,
+            table: Arc::new(table),
+            topology: Topology::default(),
+        }
+    }
+
+    /// Joins the edges the given way instead of as a torus.
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn table(&self) -> &RuleTable {
+        &self.table
+    }
+
+    pub fn cells(&self) -> &Array2<State> {
+        &self.cells
+    }
+
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+
+    /// Copies the given cells with their top left corner at `offset`.
+    pub fn insert(&mut self, offset: (usize, usize), cells: &Array2<State>) {
+        let (x, y) = offset;
+        let (n, m) = cells.dim();
+
+        if n + x > self.size.0 || m + y > self.size.1 {
+            panic!("Pattern size {:?} with offset {:?} overflows grid of {:?}",
+                   (n, m), offset, self.size);
+        }
+
+        self.cells
+            .slice_mut(s![x as isize..(n + x) as isize, y as isize..(m + y) as isize])
+            .assign(cells);
+    }
+}
+
+
+impl Grid for Automaton {
+    type Cell = State;
+    type Coord = Coord;
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn item(&self, coord: Coord) -> State {
+        let (x, y) = coord.into();
+        self.cells[[x, y]]
+    }
+
+    /// The neighbours in the order of the table, empty beyond the edges.
+    fn item_neighbours(&self, coord: Coord) -> Vec<State> {
+        let offsets = self.table.neighbourhood().offsets();
+
+        self.topology.neighbours(coord.into(), offsets, self.size)
+            .into_iter()
+            .map(|x| x.map_or(State(0), |(x, y)| self.cells[[x, y]]))
+            .collect()
+    }
+
+    fn item_fate(&self, coord: Coord) -> State {
+        self.table.fate(self.item(coord), &self.item_neighbours(coord))
+    }
+
+    /// Remembers the fate of every configuration met, most of the grid
+    /// usually being the same few.
+    fn evolve(&self) -> Self {
+        let mut fates: HashMap<(State, Vec<State>), State> = HashMap::new();
+        let mut cells = self.cells.clone();
+
+        for ((x, y), cell) in cells.indexed_iter_mut() {
+            let key = (*cell, self.item_neighbours(Coord(x, y)));
+
+            *cell = match fates.get(&key) {
+                Some(&fate) => fate,
+                None => {
+                    let fate = self.table.fate(key.0, &key.1);
+                    fates.insert(key, fate);
+                    fate
+                }
+            };
+        }
+
+        Automaton {
+            cells: cells,
+            gen: self.gen + 1,
+            table: self.table.clone(),
+            .. *self
+        }
+    }
+}
+
+
+impl IntoIterator for Automaton {
+    type Item = (Coord, State);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.iter()
+            .enumerate()
+            .map(|(i, &x)| (Coord::from_row_major(i, self.size.1), x))
+            .collect::<Vec<(Coord, State)>>()
+            .into_iter()
+    }
+}
+
+
+impl PartialEq for Automaton {
+    fn eq(&self, other: &Automaton) -> bool {
+        self.cells == other.cells
+    }
+}
+
+
+impl fmt::Display for Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let res = self.cells.iter()
+            .map(|x| format!("{}", x))
+            .chunks(self.size.1)
+            .into_iter()
+            .map(|xs| format!("{}\n", xs.collect::<String>()))
+            .collect::<String>();
+
+        write!(f, "{}", res)
+    }
+}
+
+
+#[test]
+fn test_wireworld() {
+    use table::{ self, WIREWORLD };
+
+    // An electron going right along a wire, A being its head and B its tail.
+    let cells = |s: &str| {
+        let xs: Vec<State> = s.chars().map(|c| c.to_string().parse().unwrap()).collect();
+        Array2::from_shape_vec((1, xs.len()), xs).unwrap()
+    };
+
+    let mut grid = Automaton::new(table::parse(WIREWORLD).unwrap(), 3, 10)
+        .with_topology(Topology::Plane);
+    grid.insert((1, 0), &cells("BACCCCCCCC"));
+
+    for _ in 0..5 {
+        grid = grid.evolve();
+    }
+
+    let mut expected = Automaton::new(table::parse(WIREWORLD).unwrap(), 3, 10);
+    expected.insert((1, 0), &cells("CCCCCBACCC"));
+
+    assert_eq!(grid, expected);
+    assert_eq!(grid.generation(), 6);
+    assert_eq!(grid.item_fate(Coord(1, 6)), State(2));
+
+    // Off the end of the wire on a plane, around it on a torus.
+    for _ in 0..3 {
+        grid = grid.evolve();
+    }
+
+    let row = |x: &Automaton| format!("{}", x).lines().nth(1).unwrap().to_string();
+
+    assert_eq!(row(&grid), "CCCCCCCCBA");
+    assert_eq!(row(&grid.evolve()), "CCCCCCCCCB");
+    assert_eq!(row(&grid.with_topology(Topology::Torus).evolve()), "ACCCCCCCCB");
+}
@@ -0,0 +1,20 @@
+extern crate conway;
+
+use std::env;
+
+use conway::soup::Search;
+
+
+/// Usage: soup <seed> [soups] [report]
+fn main() {
+    let mut args = env::args().skip(1);
+    let seed = args.next().unwrap_or_else(|| "k_conway".into());
+    let soups = args.next().and_then(|x| x.parse().ok()).unwrap_or(1000);
+    let path = args.next().unwrap_or_else(|| "soup_report.txt".into());
+
+    let report = Search::new(&seed, soups).run();
+    report.write(&path).expect("Unable to write the report");
+
+    println!("{} soups searched, {} objects found, report written to {}",
+             report.soups, report.counts.values().sum::<usize>(), path);
+}
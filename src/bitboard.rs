@@ -0,0 +1,444 @@
+//! Grid implementation using rows of bits packed in `u64` words.
+//!
+//! Neighbour counts are computed for 64 cells at once with bitwise adders,
+//! so evolving doesn't allocate per cell. Only totalistic Life-like rules
+//! are supported; dead cells don't keep track of their age.
+
+use std::fmt;
+use itertools::Itertools;
+use ndarray::prelude::*;
+
+use coord::Dim2 as Coord;
+use cell::Cell;
+use community::Community;
+use grid::{ Grid, inc, dec };
+use neighbourhood::Neighbourhood;
+use pattern::{ Pattern, Layout, Random };
+use rule::Rule;
+use topology::Topology;
+
+
+const BITS: usize = 64;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitboard {
+    words: Vec<u64>,
+    size: (usize, usize),
+    gen: usize,
+    rule: Rule,
+}
+
+impl Bitboard {
+    pub fn empty(n: usize) -> Self {
+        Bitboard::rectangle(n, n)
+    }
+
+    pub fn rectangle(rows: usize, cols: usize) -> Self {
+        Bitboard {
+            words: vec![0; rows * words_per_row(cols)],
+            size: (rows, cols),
+            gen: 1,
+            rule: Rule::default(),
+        }
+    }
+
+    pub fn random(n: usize) -> Self {
+        let mut grid = Bitboard::empty(n);
+        grid.insert(Layout::new((0, 0), Random(n, n)));
+
+        grid
+    }
+
+    /// A random grid, the same as `Community::seeded` with the same
+    /// arguments.
+    pub fn seeded(n: usize, density: f64, seed: u64) -> Self {
+        let mut grid = Bitboard::empty(n);
+        grid.insert(Layout::new((0, 0), Random(n, n).with_seed(seed).with_density(density)));
+
+        grid
+    }
+
+    /// The cells of the community, failing if it evolves in a way the
+    /// bitboard can't: bitboards are tori counting the 8 cells around.
+    pub fn from_community(grid: &Community) -> Result<Self, String> {
+        let rule = grid.rule();
+
+        if let Some(ltl) = grid.ltl() {
+            return Err(format!("Bitboard does not support the rule {}", ltl));
+        }
+
+        if grid.topology() != Topology::Torus {
+            return Err(format!("Bitboard does not support the topology {:?}", grid.topology()));
+        }
+
+        if *grid.neighbourhood() != Neighbourhood::default() {
+            return Err(format!("Bitboard does not support the neighbourhood {:?}",
+                               grid.neighbourhood()));
+        }
+
+        let (n, m) = grid.size();
+        let mut board = Bitboard::rectangle(n, m).try_with_rule(rule)?;
+
+        for (x, y) in iproduct!(0..n, 0..m) {
+            board.set(x, y, grid.item((x, y).into()) == Cell::Alive);
+        }
+
+        Ok(board)
+    }
+
+    /// Evolves with the given rule instead of Conway's.
+    ///
+    /// Panics if the rule is a Generations or non-totalistic rule, see
+    /// `try_with_rule`.
+    pub fn with_rule(self, rule: Rule) -> Self {
+        self.try_with_rule(rule).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Evolves with the given rule instead of Conway's, failing if the
+    /// bitboard doesn't support it.
+    pub fn try_with_rule(mut self, rule: Rule) -> Result<Self, String> {
+        if !Bitboard::supports(rule) {
+            return Err(format!("Bitboard does not support the rule {}", rule));
+        }
+
+        self.rule = rule;
+        Ok(self)
+    }
+
+    /// Whether the rule is a totalistic two state one.
+    pub fn supports(rule: Rule) -> bool {
+        rule.states() <= 2 && rule.is_totalistic()
+    }
+
+    pub fn regenerate<T: Into<Coord>>(&mut self, coord: T) {
+        let (x, y) = coord.into().into();
+        self.set(x, y, true);
+    }
+
+    /// Mix in a pattern
+    pub fn insert<T: Pattern<Array2<Cell>>>(&mut self, layout: Layout<T>) {
+        let (x, y) = layout.offset();
+        let (n, m) = layout.size();
+
+        if n + x > self.size.0 || m + y > self.size.1 {
+            panic!("Pattern size {:?} with offset {:?} overflows grid of {:?}",
+                   layout.size(), layout.offset(), self.size);
+        }
+
+        for ((i, j), cell) in layout.pattern().indexed_iter() {
+            self.set(x + i, y + j, *cell == Cell::Alive);
+        }
+    }
+
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+
+    /// Number of alive cells.
+    pub fn population(&self) -> usize {
+        self.words.iter().map(|x| x.count_ones() as usize).sum()
+    }
+
+    fn row_words(&self) -> usize {
+        words_per_row(self.size.1)
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        let word = self.words[x * self.row_words() + y / BITS];
+
+        word & (1 << (y % BITS)) != 0
+    }
+
+    fn set(&mut self, x: usize, y: usize, alive: bool) {
+        let i = x * self.row_words() + y / BITS;
+        let bit = 1 << (y % BITS);
+
+        if alive {
+            self.words[i] |= bit;
+        } else {
+            self.words[i] &= !bit;
+        }
+    }
+
+    /// Fills `out` with the row shifted so that each cell holds the value of
+    /// its west neighbour.
+    fn west(&self, row: &[u64], out: &mut [u64]) {
+        let width = self.size.1;
+        let mut carry = (row[(width - 1) / BITS] >> ((width - 1) % BITS)) & 1;
+
+        for (x, &word) in out.iter_mut().zip(row) {
+            *x = (word << 1) | carry;
+            carry = word >> (BITS - 1);
+        }
+
+        if let Some(last) = out.last_mut() {
+            *last &= last_mask(width);
+        }
+    }
+
+    /// Fills `out` with the row shifted so that each cell holds the value of
+    /// its east neighbour.
+    fn east(&self, row: &[u64], out: &mut [u64]) {
+        let width = self.size.1;
+
+        for w in 0..row.len() {
+            let carry = if w + 1 < row.len() { row[w + 1] << (BITS - 1) } else { 0 };
+            out[w] = (row[w] >> 1) | carry;
+        }
+
+        out[(width - 1) / BITS] |= (row[0] & 1) << ((width - 1) % BITS);
+    }
+}
+
+
+fn words_per_row(width: usize) -> usize {
+    (width + BITS - 1) / BITS
+}
+
+/// Mask of the valid bits in the last word of a row.
+fn last_mask(width: usize) -> u64 {
+    match width % BITS {
+        0 => !0,
+        n => (1 << n) - 1,
+    }
+}
+
+fn half_adder(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+fn full_adder(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let t = a ^ b;
+
+    (t ^ c, (a & b) | (t & c))
+}
+
+/// Adds 8 one bit numbers per bit position, returning the four bits of the
+/// sum from least to most significant.
+fn count(xs: [u64; 8]) -> [u64; 4] {
+    let (s1, c1) = full_adder(xs[0], xs[1], xs[2]);
+    let (s2, c2) = full_adder(xs[3], xs[4], xs[5]);
+    let (s3, c3) = half_adder(xs[6], xs[7]);
+    let (b0, c4) = full_adder(s1, s2, s3);
+    let (t, c5) = full_adder(c1, c2, c3);
+    let (b1, c6) = half_adder(t, c4);
+    let (b2, b3) = half_adder(c5, c6);
+
+    [b0, b1, b2, b3]
+}
+
+
+impl Grid for Bitboard {
+    type Cell = Cell;
+    type Coord = Coord;
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+
+    fn item(&self, coord: Coord) -> Cell {
+        let (x, y) = coord.into();
+
+        self.get(x, y).into()
+    }
+
+
+    fn item_neighbours(&self, coord: Coord) -> Vec<Cell> {
+        let (x, y) = coord.into();
+        let (n, m) = self.size;
+
+        vec![
+            self.get(x, dec(y, m)),
+            self.get(x, inc(y, m)),
+            self.get(dec(x, n), y),
+            self.get(inc(x, n), y),
+            self.get(dec(x, n), dec(y, m)),
+            self.get(dec(x, n), inc(y, m)),
+            self.get(inc(x, n), dec(y, m)),
+            self.get(inc(x, n), inc(y, m)),
+        ].into_iter().map(|x| x.into()).collect()
+    }
+
+    fn evolve(&self) -> Self {
+        let (n, _) = self.size;
+        let words = self.row_words();
+
+        // Without columns there are no rows of words to shift.
+        if words == 0 {
+            return Bitboard {
+                gen: self.gen + 1,
+                .. self.clone()
+            };
+        }
+
+        let mut west = vec![0; self.words.len()];
+        let mut east = vec![0; self.words.len()];
+
+        for (i, row) in self.words.chunks(words).enumerate() {
+            self.west(row, &mut west[i * words..(i + 1) * words]);
+            self.east(row, &mut east[i * words..(i + 1) * words]);
+        }
+
+        let rows: Vec<&[u64]> = self.words.chunks(words).collect();
+        let wests: Vec<&[u64]> = west.chunks(words).collect();
+        let easts: Vec<&[u64]> = east.chunks(words).collect();
+
+        // Neighbour counts leading to a birth or a survival.
+        let births: Vec<usize> = (0..9).filter(|&x| self.rule.is_born(x)).collect();
+        let survivals: Vec<usize> = (0..9).filter(|&x| self.rule.survives(x)).collect();
+        let matches = |sum: &[u64; 4], count: usize| {
+            (0..4).fold(!0, |acc, i| {
+                if count & (1 << i) != 0 { acc & sum[i] } else { acc & !sum[i] }
+            })
+        };
+
+        let mut next = Vec::with_capacity(self.words.len());
+
+        for x in 0..n {
+            let (up, down) = (dec(x, n), inc(x, n));
+
+            for w in 0..words {
+                let sum = count([
+                    wests[up][w], rows[up][w], easts[up][w],
+                    wests[x][w],               easts[x][w],
+                    wests[down][w], rows[down][w], easts[down][w],
+                ]);
+                let alive = rows[x][w];
+
+                let born = births.iter().fold(0, |acc, &c| acc | matches(&sum, c));
+                let survive = survivals.iter().fold(0, |acc, &c| acc | matches(&sum, c));
+
+                next.push((!alive & born) | (alive & survive));
+            }
+
+            // Births on 0 neighbours would spill over the row padding.
+            if let Some(last) = next.last_mut() {
+                *last &= last_mask(self.size.1);
+            }
+        }
+
+        Bitboard {
+            words: next,
+            size: self.size,
+            gen: self.gen + 1,
+            rule: self.rule,
+        }
+    }
+}
+
+
+impl IntoIterator for Bitboard {
+    type Item = (Coord, Cell);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let (n, m) = self.size;
+
+        iproduct!(0..n, 0..m)
+            .enumerate()
+            .map(|(i, (x, y))| (Coord::from_row_major(i, m), self.get(x, y).into()))
+            .collect::<Vec<(Coord, Cell)>>()
+            .into_iter()
+    }
+}
+
+
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (n, m) = self.size;
+        let res = iproduct!(0..n, 0..m)
+            .map(|(x, y)| format!("{}", Cell::from(self.get(x, y))))
+            .chunks(m)
+            .into_iter()
+            .map(|xs| format!("{}\n", xs.collect::<String>()))
+            .collect::<String>();
+
+        write!(f, "{}", res)
+    }
+}
+
+
+#[test]
+fn test_count() {
+    let xs = [0b1, 0b11, 0b111, 0b1111, 0b11111, 0b111111, 0b1111111, 0b11111111];
+    let sum = count(xs);
+
+    for i in 0..8 {
+        let n = (0..4).fold(0, |acc, b| acc | (((sum[b] >> i) & 1) << b));
+        assert_eq!(n, 8 - i);
+    }
+}
+
+#[test]
+fn test_evolve_like_community() {
+    for &(n, rule) in &[(70, "B3/S23"), (64, "B36/S23"), (130, "B3678/S34678")] {
+        let rule: Rule = rule.parse().unwrap();
+        let mut community = Community::empty(n).with_rule(rule);
+        community.insert(Layout::new((0, 0), Random(n, n).with_seed(n as u64)));
+
+        let mut board = Bitboard::from_community(&community).unwrap();
+
+        for _ in 0..10 {
+            community = community.evolve();
+            board = board.evolve();
+        }
+
+        for (x, y) in iproduct!(0..n, 0..n) {
+            let expected = community.item((x, y).into()) == Cell::Alive;
+            assert_eq!(board.get(x, y), expected, "cell {:?} with {}", (x, y), rule);
+        }
+    }
+}
+
+#[test]
+fn test_glider_wraps() {
+    use pattern::Glider;
+
+    let n = 70;
+    let mut board = Bitboard::empty(n);
+    board.insert(Layout::new((60, 60), Glider::BottomRight));
+    let start = board.clone();
+
+    for _ in 0..4 * n {
+        board = board.evolve();
+    }
+
+    assert_eq!(board.population(), 5);
+    assert_eq!(board.words, start.words);
+}
+
+#[test]
+fn test_from_community() {
+    let xs = vec![
+        Community::empty(8).with_rule("B3/S23/C3".parse().unwrap()),
+        Community::empty(8).with_topology(Topology::Plane),
+        Community::empty(8).with_topology(Topology::KleinBottle),
+        Community::empty(8).with_neighbourhood(Neighbourhood::Hexagonal),
+        Community::empty(8).with_ltl("R1,C0,M0,S2..3,B3..3,NM".parse().unwrap()),
+    ];
+
+    for community in xs {
+        let err = Bitboard::from_community(&community).unwrap_err();
+        assert!(err.contains("does not support"), "{}", err);
+    }
+
+    assert!(Bitboard::from_community(&Community::empty(8)).is_ok());
+    assert!(Bitboard::empty(8).try_with_rule("B2-a/S12".parse().unwrap()).is_err());
+}
+
+#[test]
+fn test_empty() {
+    for &(n, m) in &[(0, 0), (0, 5), (5, 0)] {
+        let board = Bitboard::rectangle(n, m).evolve();
+
+        assert_eq!(board.generation(), 2);
+        assert_eq!(board.population(), 0);
+    }
+}
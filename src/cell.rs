@@ -40,6 +40,13 @@ impl GridItem for Cell {
         }
     }
 
+    fn age(&self) -> Option<usize> {
+        match *self {
+            Cell::Dead(x) => Some(x),
+            _ => None,
+        }
+    }
+
     fn keep(&self) -> Cell {
         *self
     }
@@ -69,8 +76,8 @@ impl GridItem for Cell {
 
 impl PartialEq for Cell {
     fn eq(&self, other: &Cell) -> bool {
-        self.is_alive() == other.is_alive() ||
-        self.is_dead() == other.is_dead() ||
+        self.is_alive() == other.is_alive() &&
+        self.is_dead() == other.is_dead() &&
         self.is_unborn() == other.is_unborn()
     }
 }
@@ -119,3 +126,107 @@ impl FromStr for Cell {
         }
     }
 }
+
+
+/// A cell of a multi-state automaton, see `table`. State 0 is empty, any
+/// other one counts as alive.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct State(pub u8);
+
+impl GridItem for State {
+    fn is_alive(&self) -> bool {
+        self.0 != 0
+    }
+
+    fn age(&self) -> Option<usize> {
+        None
+    }
+
+    fn keep(&self) -> State {
+        *self
+    }
+
+    fn kill(&self) -> State {
+        State(0)
+    }
+
+    fn rot(&self) -> State {
+        *self
+    }
+
+    fn revive(&self) -> State {
+        State(1)
+    }
+}
+
+/// Written the way multi-state RLE does: `.` for 0, `A` to `X` for 1 to 24,
+/// then `pA` to `pX` for 25 to 48 and so on.
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 == 0 {
+            return write!(f, ".");
+        }
+
+        let n = self.0 - 1;
+
+        if n >= 24 {
+            write!(f, "{}", (b'o' + n / 24) as char)?;
+        }
+
+        write!(f, "{}", (b'A' + n % 24) as char)
+    }
+}
+
+impl FromStr for State {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<State, String> {
+        let bytes = s.as_bytes();
+        let letter = |c: u8| if c >= b'A' && c <= b'X' { Some(c - b'A' + 1) } else { None };
+
+        let state = match bytes.len() {
+            1 if bytes[0] == b'.' || bytes[0] == b'b' => Some(0),
+            1 if bytes[0] == b'o' => Some(1),
+            1 => letter(bytes[0]).map(|x| x as usize),
+            2 if bytes[0] >= b'p' && bytes[0] <= b'y' => {
+                letter(bytes[1]).map(|x| (bytes[0] - b'o') as usize * 24 + x as usize)
+            }
+            _ => None,
+        };
+
+        match state {
+            Some(x) if x < 256 => Ok(State(x as u8)),
+            _ => Err(format!("Unknown state {:?}", s)),
+        }
+    }
+}
+
+impl From<Cell> for State {
+    fn from(x: Cell) -> State {
+        State(x.is_alive() as u8)
+    }
+}
+
+
+#[test]
+fn test_eq() {
+    assert_eq!(Cell::Dead(3), Cell::Dead(0));
+    assert!(Cell::Alive != Cell::Unborn);
+    assert!(Cell::Alive != Cell::Dead(0));
+    assert!(Cell::Dead(0) != Cell::Unborn);
+}
+
+#[test]
+fn test_state() {
+    let xs = vec![(0, "."), (1, "A"), (24, "X"), (25, "pA"), (48, "pX"), (49, "qA"), (255, "yO")];
+
+    for (n, s) in xs {
+        assert_eq!(State(n).to_string(), s);
+        assert_eq!(s.parse(), Ok(State(n)));
+    }
+
+    assert_eq!("b".parse(), Ok(State(0)));
+    assert_eq!("o".parse(), Ok(State(1)));
+    assert!("yP".parse::<State>().is_err());
+    assert!("Z".parse::<State>().is_err());
+}
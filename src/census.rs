@@ -0,0 +1,352 @@
+//! Object census.
+//!
+//! Splits the alive cells of a grid into objects, groups of cells close to
+//! each other, and names them against a catalog of known shapes in every
+//! orientation and phase. Objects missing from the catalog are told apart as
+//! still lifes, oscillators or spaceships.
+
+use std::collections::{ BTreeMap, HashMap, HashSet, VecDeque };
+use ndarray::prelude::*;
+
+use analysis::{ self, Recurrence, Shape };
+use cell::Cell;
+use colony::Colony;
+use coord::Point;
+use grid::{ Grid, GridItem };
+use pattern::*;
+use rule::Rule;
+
+
+/// Distance used by default to tell objects apart. Parts of a pulsar are two
+/// cells away from each other. Halves of the pentadecathlon are up to seven
+/// apart, but two away in some of its phases: `Census` keeps it whole by
+/// following objects over the longest period of its catalog.
+pub const DISTANCE: usize = 2;
+
+/// Longest period looked for when adding a pattern to a catalog or telling
+/// an unknown object apart.
+const MAX_PERIOD: usize = 60;
+
+
+/// Splits the alive cells of the grid into objects: cells within `distance`
+/// rows and columns of each other belong to the same object. Grids wrapping
+/// around split objects crossing their edges.
+pub fn objects<G, C>(grid: G, distance: usize) -> Vec<Shape>
+    where G: Grid + IntoIterator<Item = (C, <G as Grid>::Cell)>,
+          C: Into<Point> {
+    objects_over(grid, distance, 0)
+}
+
+/// Like `objects`, but cells also belong to the same object when they come
+/// within `distance` of each other during the next `generations` generations.
+/// A cell is followed through the cells next to it in the generation after.
+pub fn objects_over<G, C>(grid: G, distance: usize, generations: usize) -> Vec<Shape>
+    where G: Grid + IntoIterator<Item = (C, <G as Grid>::Cell)>,
+          C: Into<Point> {
+    let mut pending: HashSet<(usize, Point)> = HashSet::new();
+    let mut grid = grid;
+
+    for gen in 0..generations + 1 {
+        if gen > 0 {
+            grid = grid.evolve();
+        }
+
+        pending.extend(grid.clone()
+                           .into_iter()
+                           .filter(|&(_, ref cell)| cell.is_alive())
+                           .map(|(coord, _)| (gen, coord.into())));
+    }
+
+    let d = distance as isize;
+    let mut starts: Vec<Point> = pending.iter()
+        .filter(|&&(gen, _)| gen == 0)
+        .map(|&(_, point)| point)
+        .collect();
+    let mut res = vec![];
+
+    // Sorted so objects come out in reading order.
+    starts.sort();
+
+    for start in starts {
+        if !pending.remove(&(0, start)) {
+            continue;
+        }
+
+        let mut cells = vec![];
+        let mut queue = VecDeque::new();
+        queue.push_back((0, start));
+
+        while let Some((gen, point)) = queue.pop_front() {
+            if gen == 0 {
+                cells.push(point);
+            }
+
+            let around = iproduct!(-d..d + 1, -d..d + 1).map(|(dx, dy)| (gen, dx, dy));
+            let before = iproduct!(-1..2, -1..2).filter(|_| gen > 0).map(|(dx, dy)| (gen - 1, dx, dy));
+            let after = iproduct!(-1..2, -1..2).map(|(dx, dy)| (gen + 1, dx, dy));
+
+            for (gen, dx, dy) in around.chain(before).chain(after) {
+                let next = (gen, point.offset(dx, dy));
+
+                if pending.remove(&next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        res.extend(Shape::new(cells));
+    }
+
+    res
+}
+
+
+/// What an object does over time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    /// Changes or dies out within `MAX_PERIOD` generations.
+    Unstable,
+}
+
+impl Kind {
+    /// The kind of the object with the given shape, under the given rule.
+    /// Rules with birth on 0 neighbours fill the plane around any object, so
+    /// they make it unstable.
+    pub fn of(shape: &Shape, rule: Rule) -> Self {
+        let grid = match Colony::new(shape.cells().iter().cloned(), 1).try_with_rule(rule) {
+            Ok(x) => x,
+            Err(_) => return Kind::Unstable,
+        };
+
+        match analysis::recurrence(&grid, MAX_PERIOD) {
+            Some(Recurrence { period: 1, dx: 0, dy: 0 }) => Kind::StillLife,
+            Some(ref x) if x.is_spaceship() => Kind::Spaceship,
+            Some(_) => Kind::Oscillator,
+            None => Kind::Unstable,
+        }
+    }
+}
+
+
+/// Known objects by canonical shape.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    names: HashMap<Vec<Point>, String>,
+    period: usize,
+}
+
+impl Catalog {
+    pub fn empty() -> Self {
+        Catalog {
+            names: HashMap::new(),
+            period: 1,
+        }
+    }
+
+    /// Adds the pattern under the given name, together with every other phase
+    /// of it.
+    pub fn add<T: Pattern<Array2<Cell>>>(&mut self, name: &str, pattern: T) {
+        let mut grid = Colony::empty();
+        grid.insert(Layout::new((0, 0), pattern));
+
+        let period = analysis::recurrence(&grid, MAX_PERIOD).map_or(1, |x| x.period);
+        self.period = self.period.max(period);
+
+        for _ in 0..period {
+            if let Some(shape) = Shape::of(grid.clone()) {
+                self.names.insert(shape.canonical().cells().to_vec(), name.to_string());
+            }
+
+            grid = grid.evolve();
+        }
+    }
+
+    /// The name of the object, in any orientation and phase.
+    pub fn name(&self, shape: &Shape) -> Option<&str> {
+        self.names.get(shape.canonical().cells()).map(|x| x.as_str())
+    }
+
+    /// The longest period of the objects added.
+    pub fn period(&self) -> usize {
+        self.period
+    }
+}
+
+/// The still lifes, oscillators and spaceships of the `pattern` module.
+impl Default for Catalog {
+    fn default() -> Self {
+        let mut catalog = Catalog::empty();
+
+        catalog.add("block", Block);
+        catalog.add("beehive", Beehive);
+        catalog.add("loaf", Loaf);
+        catalog.add("boat", Boat);
+        catalog.add("tub", Tub);
+        catalog.add("blinker", Blinker);
+        catalog.add("toad", Toad);
+        catalog.add("beacon", Beacon);
+        catalog.add("pulsar", Pulsar);
+        catalog.add("pentadecathlon", Pentadecathlon);
+        catalog.add("glider", Glider::BottomRight);
+        catalog.add("lightweight spaceship", LightweightSpaceship::Right);
+
+        catalog
+    }
+}
+
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Census {
+    /// Number of objects found in the catalog, by name.
+    pub known: BTreeMap<String, usize>,
+    /// Objects not in the catalog, in canonical form, with their kind.
+    pub unknown: Vec<(Shape, Kind)>,
+}
+
+impl Census {
+    /// Counts the objects of the grid. Objects are followed over the longest
+    /// period of the catalog, so those coming apart in some of their phases
+    /// are still counted once. Unknown objects are told apart under the rule
+    /// of the grid.
+    pub fn new<G, C>(grid: G, distance: usize, catalog: &Catalog) -> Self
+        where G: Grid + IntoIterator<Item = (C, <G as Grid>::Cell)>,
+              C: Into<Point> {
+        let mut census = Census::default();
+        let rule = grid.rule();
+
+        for shape in objects_over(grid, distance, catalog.period() - 1) {
+            match catalog.name(&shape) {
+                Some(name) => *census.known.entry(name.to_string()).or_insert(0) += 1,
+                None => census.unknown.push((shape.canonical(), Kind::of(&shape, rule))),
+            }
+        }
+
+        census
+    }
+
+    /// Number of objects found.
+    pub fn total(&self) -> usize {
+        self.known.values().sum::<usize>() + self.unknown.len()
+    }
+
+    /// Number of objects not in the catalog of the given kind.
+    pub fn unknown(&self, kind: Kind) -> usize {
+        self.unknown.iter().filter(|&&(_, x)| x == kind).count()
+    }
+}
+
+
+#[test]
+fn test_objects() {
+    use population::Population;
+
+    let mut grid = Population::empty(12);
+    grid.insert(Layout::new((1, 1), Block));
+    grid.insert(Layout::new((1, 5), Block));
+
+    assert_eq!(objects(grid.clone(), 1).len(), 2);
+    assert_eq!(objects(grid.clone(), 2).len(), 2);
+    assert_eq!(objects(grid.clone(), 3).len(), 1);
+    assert_eq!(objects(grid, 1)[1].origin(), Point(1, 5));
+}
+
+#[test]
+fn test_catalog() {
+    let catalog = Catalog::default();
+    let shape = |x: Array2<Cell>| {
+        let points = x.indexed_iter()
+            .filter(|&(_, cell)| cell.is_alive())
+            .map(|((x, y), _)| Point(x as isize, y as isize));
+
+        Shape::new(points).unwrap()
+    };
+
+    let mut boat = Boat.pattern();
+    boat.invert_axis(Axis(0));
+
+    assert_eq!(catalog.name(&shape(boat)), Some("boat"));
+    assert_eq!(catalog.name(&shape(Glider::TopLeft.pattern())), Some("glider"));
+    assert_eq!(catalog.name(&shape(LightweightSpaceship::Top.pattern())),
+               Some("lightweight spaceship"));
+    assert_eq!(catalog.name(&shape(arr2(&[[Cell::Alive]]))), None);
+}
+
+#[test]
+fn test_kind() {
+    let shape = |cells: &[(isize, isize)]| {
+        Shape::new(cells.iter().map(|&(x, y)| Point(x, y))).unwrap()
+    };
+
+    let ship = shape(&[(0, 0), (0, 1), (1, 0), (1, 2), (2, 1), (2, 2)]);
+    let blinker = shape(&[(0, 0), (0, 1), (0, 2)]);
+    let glider = shape(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+
+    let conway = Rule::conway();
+    let highlife: Rule = "B36/S23".parse().unwrap();
+    let seeds: Rule = "B2/S".parse().unwrap();
+
+    assert_eq!(Kind::of(&ship, conway), Kind::StillLife);
+    assert_eq!(Kind::of(&blinker, conway), Kind::Oscillator);
+    assert_eq!(Kind::of(&glider, conway), Kind::Spaceship);
+    assert_eq!(Kind::of(&shape(&[(0, 0)]), conway), Kind::Unstable);
+
+    assert_eq!(Kind::of(&glider, highlife), Kind::Spaceship);
+    assert_eq!(Kind::of(&ship, seeds), Kind::Unstable);
+    assert_eq!(Kind::of(&ship, "B0/S8".parse().unwrap()), Kind::Unstable);
+}
+
+#[test]
+fn test_census_rule() {
+    use population::Population;
+
+    // A block is still under Conway's rule, but dies without survival.
+    let mut grid = Population::empty(16);
+    grid.insert(Layout::new((6, 6), Block));
+
+    let census = Census::new(grid.clone(), DISTANCE, &Catalog::empty());
+    assert_eq!(census.unknown(Kind::StillLife), 1);
+
+    let grid = grid.with_rule("B3/S".parse().unwrap());
+    let census = Census::new(grid, DISTANCE, &Catalog::empty());
+    assert_eq!(census.unknown(Kind::Unstable), 1);
+}
+
+#[test]
+fn test_census() {
+    use community::Community;
+
+    let ship = arr2(&[[Cell::Alive, Cell::Alive, Cell::Unborn],
+                      [Cell::Alive, Cell::Unborn, Cell::Alive],
+                      [Cell::Unborn, Cell::Alive, Cell::Alive]]);
+
+    let mut grid = Community::empty(64);
+    grid.insert(Layout::new((1, 1), Block));
+    grid.insert(Layout::new((1, 10), Block));
+    grid.insert(Layout::new((1, 20), Beehive));
+    grid.insert(Layout::new((10, 1), Pulsar));
+    grid.insert(Layout::new((10, 25), Pentadecathlon));
+    grid.insert(Layout::new((32, 1), Toad));
+    grid.insert(Layout::new((40, 40), Custom(ship)));
+    grid.insert(Layout::new((60, 10), Glider::TopRight));
+
+    let catalog = Catalog::default();
+    assert_eq!(catalog.period(), 15);
+
+    // Census taken in every phase of the grid, the pentadecathlon included.
+    for _ in 0..60 {
+        let census = Census::new(grid.clone(), DISTANCE, &catalog);
+        let names: Vec<(&str, usize)> = census.known.iter()
+            .map(|(name, &n)| (name.as_str(), n))
+            .collect();
+
+        assert_eq!(names, vec![("beehive", 1), ("block", 2), ("glider", 1),
+                               ("pentadecathlon", 1), ("pulsar", 1), ("toad", 1)]);
+        assert_eq!(census.unknown(Kind::StillLife), 1);
+        assert_eq!(census.total(), 8);
+
+        grid = grid.evolve();
+    }
+}
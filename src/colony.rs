@@ -0,0 +1,321 @@
+//! Grid implementation using a `HashSet` of alive cells.
+//!
+//! The grid has no bounds, patterns grow as far as they need without wrapping
+//! around. Only alive and dying cells are stored, so rules with birth on 0
+//! neighbours (B0) are not supported.
+
+use std::collections::{ HashMap, HashSet };
+use std::fmt;
+use ndarray::prelude::*;
+
+use coord::Point as Coord;
+use cell::Cell;
+use grid::Grid;
+use neighbourhood::Neighbourhood;
+use pattern::{ Pattern, Layout };
+use rule::Rule;
+
+
+#[derive(Debug, Clone, Eq)]
+pub struct Colony {
+    alive: HashSet<Coord>,
+    dying: HashMap<Coord, usize>,
+    gen: usize,
+    rule: Rule,
+    neighbourhood: Neighbourhood,
+}
+
+impl Colony {
+    pub fn new<I: IntoIterator<Item = Coord>>(alive: I, gen: usize) -> Self {
+        Colony {
+            alive: alive.into_iter().collect(),
+            dying: HashMap::new(),
+            gen: gen,
+            rule: Rule::default(),
+            neighbourhood: Neighbourhood::default(),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Colony::new(vec![], 1)
+    }
+
+    /// Evolves with the given rule instead of Conway's.
+    ///
+    /// Panics if the rule has birth on 0 neighbours, see `try_with_rule`.
+    pub fn with_rule(self, rule: Rule) -> Self {
+        self.try_with_rule(rule).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Evolves with the given rule instead of Conway's, failing if it has
+    /// birth on 0 neighbours: every empty cell of the infinite plane would
+    /// be born.
+    pub fn try_with_rule(mut self, rule: Rule) -> Result<Self, String> {
+        if rule.is_born(0) {
+            return Err(format!("Colony does not support the rule {}", rule));
+        }
+
+        self.rule = rule;
+        Ok(self)
+    }
+
+    /// Counts the given neighbours instead of the 8 around each cell.
+    pub fn with_neighbourhood(mut self, neighbourhood: Neighbourhood) -> Self {
+        self.neighbourhood = neighbourhood;
+        self
+    }
+
+    pub fn regenerate<T: Into<Coord>>(&mut self, coord: T) {
+        let coord = coord.into();
+        self.dying.remove(&coord);
+        self.alive.insert(coord);
+    }
+
+    /// Mix in a pattern
+    pub fn insert<T: Pattern<Array2<Cell>>>(&mut self, layout: Layout<T>) {
+        let (x, y) = layout.offset();
+        let pattern = layout.pattern();
+
+        for ((i, j), cell) in pattern.indexed_iter() {
+            let coord = Coord((x + i) as isize, (y + j) as isize);
+
+            match *cell {
+                Cell::Alive => self.regenerate(coord),
+                Cell::Dead(age) if self.rule.is_refractory(age) => {
+                    self.alive.remove(&coord);
+                    self.dying.insert(coord, age);
+                }
+                _ => {
+                    self.alive.remove(&coord);
+                    self.dying.remove(&coord);
+                }
+            }
+        }
+    }
+
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+
+    /// Number of alive cells.
+    pub fn population(&self) -> usize {
+        self.alive.len()
+    }
+
+    /// The smallest rectangle holding every alive and dying cell, as its top
+    /// left and bottom right corners (inclusive).
+    pub fn bounds(&self) -> Option<(Coord, Coord)> {
+        let mut coords = self.alive.iter().chain(self.dying.keys());
+        let first = match coords.next() {
+            Some(x) => *x,
+            None => return None,
+        };
+
+        Some(coords.fold((first, first), |(min, max), c| {
+            (Coord(min.0.min(c.0), min.1.min(c.1)),
+             Coord(max.0.max(c.0), max.1.max(c.1)))
+        }))
+    }
+
+    fn coords(&self) -> Vec<Coord> {
+        match self.bounds() {
+            Some((min, max)) => {
+                iproduct!(min.0..max.0 + 1, min.1..max.1 + 1)
+                    .map(|x| x.into())
+                    .collect()
+            }
+            None => vec![],
+        }
+    }
+}
+
+
+impl Grid for Colony {
+    type Cell = Cell;
+    type Coord = Coord;
+
+    /// The size of the bounding box.
+    fn size(&self) -> (usize, usize) {
+        match self.bounds() {
+            Some((min, max)) => ((max.0 - min.0 + 1) as usize, (max.1 - min.1 + 1) as usize),
+            None => (0, 0),
+        }
+    }
+
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+
+    fn item(&self, coord: Coord) -> Cell {
+        if self.alive.contains(&coord) {
+            return Cell::Alive;
+        }
+
+        match self.dying.get(&coord) {
+            Some(&age) => Cell::Dead(age),
+            None => Cell::Unborn,
+        }
+    }
+
+
+    fn item_neighbours(&self, coord: Coord) -> Vec<Cell> {
+        self.neighbourhood.offsets()
+            .into_iter()
+            .map(|(dx, dy)| self.item(coord.offset(dx, dy)))
+            .collect()
+    }
+
+    fn evolve(&self) -> Self {
+        let mut counts: HashMap<Coord, usize> = HashMap::new();
+        let offsets = self.neighbourhood.offsets();
+
+        // Each alive cell counts for the cells it is a neighbour of.
+        for coord in &self.alive {
+            for &(dx, dy) in &offsets {
+                *counts.entry(coord.offset(-dx, -dy)).or_insert(0) += 1;
+            }
+        }
+
+        let candidates: HashSet<Coord> = counts.keys()
+            .chain(self.alive.iter())
+            .chain(self.dying.keys())
+            .cloned()
+            .collect();
+
+        let mut alive = HashSet::new();
+        let mut dying = HashMap::new();
+
+        for coord in candidates {
+            let count = counts.get(&coord).cloned().unwrap_or(0);
+
+            let fate = if self.rule.is_totalistic() {
+                self.rule.fate(self.item(coord), count)
+            } else {
+                self.item_fate(coord)
+            };
+
+            match fate {
+                Cell::Alive => {
+                    alive.insert(coord);
+                }
+                Cell::Dead(age) if self.rule.is_refractory(age) => {
+                    dying.insert(coord, age);
+                }
+                _ => {}
+            }
+        }
+
+        Colony {
+            alive: alive,
+            dying: dying,
+            gen: self.gen + 1,
+            rule: self.rule,
+            neighbourhood: self.neighbourhood.clone(),
+        }
+    }
+}
+
+
+impl IntoIterator for Colony {
+    type Item = (Coord, Cell);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    /// Every cell inside the bounding box.
+    fn into_iter(self) -> Self::IntoIter {
+        self.coords()
+            .into_iter()
+            .map(|x| (x, self.item(x)))
+            .collect::<Vec<(Coord, Cell)>>()
+            .into_iter()
+    }
+}
+
+
+impl PartialEq for Colony {
+    fn eq(&self, other: &Colony) -> bool {
+        self.alive == other.alive && self.dying == other.dying
+    }
+}
+
+
+impl fmt::Display for Colony {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (min, max) = match self.bounds() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        for x in min.0..max.0 + 1 {
+            let row = (min.1..max.1 + 1)
+                .map(|y| format!("{}", self.item(Coord(x, y))))
+                .collect::<String>();
+
+            writeln!(f, "{}", row)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[test]
+fn test_glider_unbounded() {
+    use pattern::Glider;
+
+    let mut colony = Colony::empty();
+    colony.insert(Layout::new((0, 0), Glider::BottomRight));
+
+    let shape = format!("{}", colony);
+    let mut next = colony.clone();
+
+    for _ in 0..400 {
+        next = next.evolve();
+    }
+
+    assert_eq!(next.population(), 5);
+    assert_eq!(next.generation(), 401);
+    assert_eq!(next.bounds(), Some((Coord(100, 100), Coord(102, 102))));
+    assert_eq!(format!("{}", next), shape);
+}
+
+#[test]
+fn test_bounds() {
+    let mut colony = Colony::empty();
+    assert_eq!(colony.bounds(), None);
+    assert_eq!(colony.size(), (0, 0));
+
+    colony.regenerate((-3, 2));
+    colony.regenerate((4, -1));
+
+    assert_eq!(colony.bounds(), Some((Coord(-3, -1), Coord(4, 2))));
+    assert_eq!(colony.size(), (8, 4));
+}
+
+#[test]
+fn test_dying_generations() {
+    use grid::GridItem;
+
+    let mut colony = Colony::empty().with_rule("/2/3".parse().unwrap());
+    colony.regenerate((0, 0));
+    colony.regenerate((0, 1));
+
+    let next = colony.evolve();
+
+    assert_eq!(next.item(Coord(0, 0)).age(), Some(0));
+    assert_eq!(next.item(Coord(-1, 0)), Cell::Alive);
+    assert_eq!(next.item(Coord(1, 1)), Cell::Alive);
+    assert_eq!(next.evolve().item(Coord(0, 0)), Cell::Unborn);
+}
+
+#[test]
+#[should_panic(expected = "does not support")]
+fn test_birth_on_zero() {
+    let rule = "B03/S23".parse().unwrap();
+
+    assert!(Colony::empty().try_with_rule(rule).is_err());
+    assert!(Colony::empty().try_with_rule(Rule::default()).is_ok());
+
+    Colony::empty().with_rule(rule);
+}
@@ -4,11 +4,16 @@ use itertools::Itertools;
 use ndarray::prelude::*;
 use rand::{self, Rng};
 use std::fmt;
+use std::thread;
 
 use coord::Dim2 as Coord;
 use cell::Cell;
-use grid::Grid;
+use grid::{ Grid, GridItem };
+use ltl::LargerThanLife;
 use pattern::*;
+use neighbourhood::Neighbourhood;
+use rule::{ self, Rule };
+use topology::Topology;
 
 type Matrix = Array2<Cell>;
 
@@ -17,6 +22,11 @@ pub struct Community {
     cells: Array2<Cell>,
     size: (usize, usize),
     gen: usize,
+    rule: Rule,
+    topology: Topology,
+    neighbourhood: Neighbourhood,
+    ltl: Option<LargerThanLife>,
+    threads: usize,
 }
 
 
@@ -27,12 +37,67 @@ impl Community {
         Community {
             cells: cells,
             size: size,
-            gen: gen
+            gen: gen,
+            rule: Rule::default(),
+            topology: Topology::default(),
+            neighbourhood: Neighbourhood::default(),
+            ltl: None,
+            threads: 1,
         }
     }
 
+    /// Evolves with the given rule instead of Conway's.
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    /// Joins the edges the given way instead of as a torus.
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Counts the given neighbours instead of the 8 around each cell.
+    pub fn with_neighbourhood(mut self, neighbourhood: Neighbourhood) -> Self {
+        self.neighbourhood = neighbourhood;
+        self
+    }
+
+    /// Evolves with a Larger than Life rule instead of a Life-like one,
+    /// counting neighbours over its range.
+    pub fn with_ltl(mut self, ltl: LargerThanLife) -> Self {
+        self.neighbourhood = ltl.neighbourhood();
+        self.ltl = Some(ltl);
+        self
+    }
+
+    /// Evolves using the given number of threads, each one taking care of a
+    /// band of rows.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = if threads == 0 { 1 } else { threads };
+        self
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn neighbourhood(&self) -> &Neighbourhood {
+        &self.neighbourhood
+    }
+
+    /// The Larger than Life rule, if it evolves with one.
+    pub fn ltl(&self) -> Option<LargerThanLife> {
+        self.ltl
+    }
+
     pub fn empty(n: usize) -> Self {
-        Community::new(Array2::from_elem((n as Ix, n as Ix), Cell::Unborn), 1)
+        Community::rectangle(n, n)
+    }
+
+    pub fn rectangle(rows: usize, cols: usize) -> Self {
+        Community::new(Array2::from_elem((rows as Ix, cols as Ix), Cell::Unborn), 1)
     }
 
     pub fn random(n: usize) -> Self {
@@ -42,16 +107,25 @@ impl Community {
         grid
     }
 
+    /// A random grid, the same for a given seed on every platform.
+    pub fn seeded(n: usize, density: f64, seed: u64) -> Self {
+        let mut grid = Community::empty(n);
+        grid.insert(Layout::new((0, 0), Random(n, n).with_seed(seed).with_density(density)));
+
+        grid
+    }
+
+    /// A random grid drawn from the given generator.
+    pub fn random_with<R: Rng>(n: usize, density: f64, rng: &mut R) -> Self {
+        Community::new(Random(n, n).with_density(density).fill(rng), 1)
+    }
+
     /// Mix in a pattern
     pub fn insert<T: Pattern<Array2<Cell>>>(&mut self, layout: Layout<T>) {
         let (x, y) = layout.offset();
         let (n, m) = layout.size();
 
-        if layout.size() > self.size {
-            panic!("Patterns must be smaller than the recipient grid");
-        }
-
-        if (n + x, m + y) > self.size {
+        if n + x > self.size.0 || m + y > self.size.1 {
             panic!("Pattern size {:?} with offset {:?} overflows grid of {:?}",
                    layout.size(), layout.offset(), self.size);
         }
@@ -67,6 +141,61 @@ impl Community {
             .slice_mut(s![lower_x..upper_x, lower_y..upper_y])
             .assign(&a);
     }
+
+    /// The next generation, with the same settings.
+    fn next(&self, cells: Array2<Cell>) -> Self {
+        Community {
+            ltl: self.ltl,
+            .. Community::new(cells, self.gen + 1)
+                .with_rule(self.rule)
+                .with_topology(self.topology)
+                .with_neighbourhood(self.neighbourhood.clone())
+                .with_threads(self.threads)
+        }
+    }
+
+    /// Reads every count from a summed-area table, see `ltl`.
+    fn evolve_ltl(&self, ltl: LargerThanLife) -> Self {
+        let counts = ltl.counts(&self.cells, self.topology);
+        let mut cells = self.cells.clone();
+
+        for ((x, y), cell) in cells.indexed_iter_mut() {
+            *cell = ltl.fate(*cell, counts[[x, y]]);
+        }
+
+        self.next(cells)
+    }
+
+    /// Splits the grid in bands of rows evolved in parallel. Every thread
+    /// borrows the same grid so the halo rows around each band, including
+    /// the ones across the seams, are the previous generation.
+    fn evolve_parallel(&self) -> Self {
+        let width = self.cells.rows();
+        let height = self.cells.cols();
+        // A grid without rows still needs bands of some height.
+        let band = ((width + self.threads - 1) / self.threads).max(1);
+
+        let vec: Vec<Cell> = thread::scope(|scope| {
+            let handles: Vec<thread::ScopedJoinHandle<Vec<Cell>>> = (0..width)
+                .step_by(band)
+                .map(|lower| {
+                    let upper = (lower + band).min(width);
+
+                    scope.spawn(move || {
+                        iproduct!(lower..upper, 0..height)
+                            .map(|(x, y)| self.item_fate((x, y).into()))
+                            .collect()
+                    })
+                })
+                .collect();
+
+            handles.into_iter()
+                .flat_map(|x| x.join().expect("Evolution thread panicked"))
+                .collect()
+        });
+
+        self.next(Array::from_shape_vec((width, height), vec).unwrap())
+    }
 }
 
 
@@ -74,8 +203,13 @@ impl Grid for Community {
     type Cell = Cell;
     type Coord = Coord;
 
-    fn size(&self) -> usize {
-        self.size.0
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+
+    fn rule(&self) -> Rule {
+        self.rule
     }
 
 
@@ -86,22 +220,35 @@ impl Grid for Community {
 
 
     fn item_neighbours(&self, coord: Coord) -> Vec<Cell> {
-        let (x, y) = coord.into();
-        let size = self.size();
+        self.topology.neighbours(coord.into(), &self.neighbourhood.offsets(), self.size)
+            .into_iter()
+            .map(|x| x.map_or(Cell::Unborn, |(x, y)| self.cells[[x, y]]))
+            .collect()
+    }
+
+    /// Counts neighbours one by one, `evolve` is much faster with a Larger
+    /// than Life rule.
+    fn item_fate(&self, coord: Coord) -> Cell {
+        let cell = self.item(coord);
+        let neighbours = self.item_neighbours(coord);
+        let count = neighbours.iter().filter(|x| x.is_alive()).count();
 
-        vec![
-            self.cells[[x, dec(y, size)]],
-            self.cells[[x, inc(y, size)]],
-            self.cells[[dec(x, size), y]],
-            self.cells[[inc(x, size), y]],
-            self.cells[[dec(x, size), dec(y, size)]],
-            self.cells[[dec(x, size), inc(y, size)]],
-            self.cells[[inc(x, size), dec(y, size)]],
-            self.cells[[inc(x, size), inc(y, size)]],
-        ]
+        match self.ltl {
+            Some(ltl) => ltl.fate(cell, count + (ltl.middle() && cell.is_alive()) as usize),
+            None if self.rule.is_totalistic() => self.rule.fate(cell, count),
+            None => self.rule.configuration_fate(cell, rule::configuration(&neighbours)),
+        }
     }
 
     fn evolve(&self) -> Self {
+        if let Some(ltl) = self.ltl {
+            return self.evolve_ltl(ltl);
+        }
+
+        if self.threads > 1 {
+            return self.evolve_parallel();
+        }
+
         let mut vec: Vec<Cell> = vec![];
         let width = self.cells.rows();
         let height = self.cells.cols();
@@ -110,9 +257,7 @@ impl Grid for Community {
             vec.push(self.item_fate((x, y).into()));
         }
 
-        let arr = Array::from_shape_vec((width, height), vec).unwrap();
-
-        Community::new(arr, self.gen + 1)
+        self.next(Array::from_shape_vec((width, height), vec).unwrap())
     }
 }
 
@@ -124,7 +269,7 @@ impl IntoIterator for Community {
     fn into_iter(self) -> Self::IntoIter {
         self.cells.iter()
             .enumerate()
-            .map(|(i, &x)| (Coord::from_index(i, self.size()), x))
+            .map(|(i, &x)| (Coord::from_row_major(i, self.size.1), x))
             .collect::<Vec<(Coord, Cell)>>()
             .into_iter()
     }
@@ -142,7 +287,7 @@ impl fmt::Display for Community {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let res = self.cells.clone().into_iter()
             .map(|x| format!("{}", x))
-            .chunks(self.size())
+            .chunks(self.size.1)
             .into_iter()
             .map(|xs| format!("{}\n", xs.collect::<String>()))
             .collect::<String>();
@@ -153,23 +298,82 @@ impl fmt::Display for Community {
 
 
 
-fn inc(x: usize, n: usize) -> usize {
-    let x = x + 1;
+#[test]
+fn test_rectangle() {
+    use bitboard::Bitboard;
+    use population::Population;
 
-    if x >= n {
-        x % n
-    } else {
-        x
+    let mut community = Community::rectangle(6, 20);
+    let mut population = Population::rectangle(6, 20);
+    community.insert(Layout::new((1, 15), Glider::BottomRight));
+    population.insert(Layout::new((1, 15), Glider::BottomRight));
+    let mut bitboard = Bitboard::from_community(&community).unwrap();
+
+    for _ in 0..24 {
+        community = community.evolve();
+        population = population.evolve();
+        bitboard = bitboard.evolve();
     }
+
+    // Six rows down and across the seam, six columns right.
+    let mut expected = Community::rectangle(6, 20);
+    expected.insert(Layout::new((1, 1), Glider::BottomRight));
+
+    let alive = |x: String| x.replace('_', ".");
+
+    assert_eq!(community.size(), (6, 20));
+    assert_eq!(alive(format!("{}", community)), alive(format!("{}", expected)));
+    assert_eq!(alive(format!("{}", population)), alive(format!("{}", expected)));
+    assert_eq!(alive(format!("{}", bitboard)), alive(format!("{}", expected)));
+    assert_eq!(population.into_iter().nth(21).unwrap(), (Coord(1, 1), Cell::Dead(0)));
 }
 
+#[test]
+fn test_seeded() {
+    use rand::{ SeedableRng, XorShiftRng };
+
+    let grid = Community::seeded(16, 0.5, 42);
+    let alive = |x: &Community| x.cells.iter().filter(|&&x| x == Cell::Alive).count();
+
+    assert_eq!(grid, Community::seeded(16, 0.5, 42));
+    assert!(grid != Community::seeded(16, 0.5, 43));
+    assert_eq!(alive(&grid), 141);
+    assert_eq!(alive(&Community::seeded(16, 0.0, 42)), 0);
+    assert_eq!(alive(&Community::seeded(16, 1.0, 42)), 256);
+    assert_eq!(alive(&Community::seeded(100, 0.3, 7)) / 100, 30);
+
+    // Plain random patterns are still built from their size alone.
+    let mut seeded = Community::empty(16);
+    seeded.insert(Layout::new((0, 0), Random(16, 16).with_seed(42)));
+    assert_eq!(seeded, grid);
+    assert_eq!(Random(3, 5).pattern().dim(), (3, 5));
+
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    let a = Community::random_with(16, 0.5, &mut rng);
+    let b = Community::random_with(16, 0.5, &mut rng);
+    assert!(a != b);
+}
 
-fn dec(x: usize, n: usize) -> usize {
-    if x == 0 {
-        n - 1
-    } else {
-        x - 1
+#[test]
+fn test_evolve_parallel() {
+    for &rule in &["B3/S23", "B2/S/C3"] {
+        let mut serial = Community::empty(67).with_rule(rule.parse().unwrap());
+        serial.insert(Layout::new((0, 0), Random(67, 67).with_seed(67)));
+
+        let parallel = serial.clone().with_threads(4);
+        let (mut a, mut b) = (serial, parallel);
+
+        for _ in 0..10 {
+            a = a.evolve();
+            b = b.evolve();
+        }
+
+        assert_eq!(b.threads, 4);
+        assert_eq!(format!("{:?}", a.cells), format!("{:?}", b.cells));
     }
+
+    let empty = Community::rectangle(0, 5).with_threads(4);
+    assert_eq!(empty.evolve().size(), (0, 5));
 }
 
 
@@ -421,21 +625,129 @@ impl Pattern<Matrix> for LightweightSpaceship {
 }
 
 
+impl Pattern<Matrix> for Custom {
+    fn size(&self) -> (usize, usize) {
+        (self.0.rows(), self.0.cols())
+    }
+
+    fn pattern(&self) -> Matrix {
+        self.0.clone()
+    }
+}
+
+
 impl Pattern<Matrix> for Random {
     fn size(&self) -> (usize, usize) {
         (self.0, self.1)
     }
 
     fn pattern(&self) -> Matrix {
-        let (n, m) = self.size();
-        let mut rng = rand::thread_rng();
-        let mut canvas = Array2::from_elem((n as Ix, m as Ix), Cell::Unborn);
+        self.fill(&mut rand::thread_rng())
+    }
+}
+
+impl Pattern<Matrix> for Noise {
+    fn size(&self) -> (usize, usize) {
+        Noise::size(self)
+    }
 
-        for (x, y) in  iproduct!(0..n, 0..m) {
-            let cell: bool = rng.gen();
-            canvas[[x, y]] = cell.into();
+    fn pattern(&self) -> Matrix {
+        match self.seed() {
+            Some(seed) => self.fill(&mut seeded(seed)),
+            None => self.fill(&mut rand::thread_rng()),
+        }
+    }
+}
+
+#[test]
+fn test_topology() {
+    use population::Population;
+    use topology::Topology;
+
+    let alive = |x: String| x.replace('_', ".");
+    let glider = |(rows, cols), topology, offset, glider: Matrix| {
+        let mut grid = Community::rectangle(rows, cols).with_topology(topology);
+        grid.insert(Layout::new(offset, Custom(glider)));
+        grid
+    };
+
+    let mut mirrored = Glider::BottomRight.pattern();
+    mirrored.invert_axis(Axis(0));
+
+    let xs = vec![
+        // Across the right edge into the left one.
+        (Topology::Cylinder, (20, 8), (4, 4), (12, 4), Glider::BottomRight.pattern()),
+        // Across the bottom edge, mirrored.
+        (Topology::KleinBottle, (8, 20), (4, 8), (4, 1), Glider::BottomLeft.pattern()),
+        // Across the right edge, mirrored.
+        (Topology::CrossSurface, (20, 8), (8, 4), (1, 4), mirrored),
+        // Across the bottom edge, 3 columns further.
+        (Topology::ShearedTorus(3), (8, 20), (4, 4), (4, 15), Glider::BottomRight.pattern()),
+        (Topology::Torus, (8, 20), (4, 4), (4, 12), Glider::BottomRight.pattern()),
+    ];
+
+    for (topology, size, from, to, expected) in xs {
+        let mut community = glider(size, topology, from, Glider::BottomRight.pattern());
+        let mut population = Population::rectangle(size.0, size.1).with_topology(topology);
+        population.insert(Layout::new(from, Glider::BottomRight));
+
+        for _ in 0..32 {
+            community = community.evolve();
+            population = population.evolve();
         }
 
-        canvas
+        let expected = format!("{}", glider(size, topology, to, expected));
+
+        assert_eq!(alive(format!("{}", community)), alive(expected.clone()), "{:?}", topology);
+        assert_eq!(alive(format!("{}", population)), alive(expected), "{:?}", topology);
+    }
+
+    // A glider hitting the edge of a plane settles as a block.
+    let mut grid = glider((8, 8), Topology::Plane, (2, 2), Glider::BottomRight.pattern());
+
+    for _ in 0..40 {
+        grid = grid.evolve();
+    }
+
+    let mut block = Community::empty(8);
+    block.insert(Layout::new((6, 6), Block));
+
+    assert_eq!(alive(format!("{}", grid)), alive(format!("{}", block)));
+    assert_eq!(grid.clone().with_threads(3).evolve(), grid.evolve());
+}
+
+#[test]
+fn test_ltl() {
+    use ltl::LargerThanLife;
+    use topology::Topology;
+
+    // Conway's rule, with the cell counted as its own neighbour.
+    let life: LargerThanLife = "R1,C0,M1,S3..4,B3..3,NM".parse().unwrap();
+    let mut grid = Community::empty(8).with_ltl(life);
+    let mut expected = Community::empty(8);
+    grid.insert(Layout::new((1, 1), Glider::BottomRight));
+    expected.insert(Layout::new((1, 1), Glider::BottomRight));
+
+    for _ in 0..12 {
+        grid = grid.evolve();
+        expected = expected.evolve();
+    }
+
+    assert_eq!(grid, expected);
+
+    // Summed-area tables agree with counting neighbours one by one.
+    let rules = vec![LargerThanLife::bosco(), "R3,C4,M0,S6..14,B7..10,NN".parse().unwrap()];
+
+    for (rule, topology) in iproduct!(rules, vec![Topology::Torus, Topology::Plane, Topology::KleinBottle]) {
+        let mut grid = Community::seeded(24, 0.5, 5).with_topology(topology).with_ltl(rule);
+
+        for _ in 0..4 {
+            let fates = iproduct!(0..24, 0..24).map(|x| grid.item_fate(x.into())).collect();
+            let expected = Array::from_shape_vec((24, 24), fates).unwrap();
+
+            grid = grid.evolve();
+
+            assert_eq!(grid.cells, expected, "{} on {:?}", rule, topology);
+        }
     }
 }
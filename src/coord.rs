@@ -1,7 +1,7 @@
 use grid::GridCoord;
 
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Dim2(pub usize, pub usize);
 
 impl Dim2 {
@@ -13,8 +13,10 @@ impl Dim2 {
         self.1
     }
 
-    pub fn from_index(index: usize, size: usize) -> Self {
-        From::from((index % size, index / size))
+    /// The coordinates of the nth cell of a grid stored row by row, `cols`
+    /// cells per row.
+    pub fn from_row_major(index: usize, cols: usize) -> Self {
+        From::from((index / cols, index % cols))
     }
 }
 
@@ -43,3 +45,148 @@ impl<'a> From<&'a Dim2> for (usize, usize) {
         (x.0, x.1)
     }
 }
+
+
+/// Coordinates of 3D grids, as (row, column, slice).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Dim3(pub usize, pub usize, pub usize);
+
+impl Dim3 {
+    pub fn x(&self) -> usize {
+        self.0
+    }
+
+    pub fn y(&self) -> usize {
+        self.1
+    }
+
+    pub fn z(&self) -> usize {
+        self.2
+    }
+}
+
+impl GridCoord for Dim3 {}
+
+impl From<(usize, usize, usize)> for Dim3 {
+    fn from(x: (usize, usize, usize)) -> Self {
+        Dim3(x.0, x.1, x.2)
+    }
+}
+
+impl From<Dim3> for (usize, usize, usize) {
+    fn from(x: Dim3) -> (usize, usize, usize) {
+        (x.0, x.1, x.2)
+    }
+}
+
+
+/// Axial coordinates of hexagonal grids, as (column, row). Each row is half
+/// a cell left of the one above, so the neighbours of `Hex(q, r)` are the
+/// cells left and right of it, `Hex(q, r - 1)`, `Hex(q - 1, r - 1)`,
+/// `Hex(q, r + 1)` and `Hex(q + 1, r + 1)`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Hex(pub usize, pub usize);
+
+impl Hex {
+    pub fn q(&self) -> usize {
+        self.0
+    }
+
+    pub fn r(&self) -> usize {
+        self.1
+    }
+}
+
+impl GridCoord for Hex {}
+
+impl From<(usize, usize)> for Hex {
+    fn from(x: (usize, usize)) -> Self {
+        Hex(x.0, x.1)
+    }
+}
+
+impl From<Hex> for (usize, usize) {
+    fn from(x: Hex) -> (usize, usize) {
+        (x.0, x.1)
+    }
+}
+
+
+/// Coordinates of triangular grids, as (row, column). Triangles point up
+/// and down in turn along rows and columns, `Tri(0, 0)` pointing up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Tri(pub usize, pub usize);
+
+impl Tri {
+    pub fn x(&self) -> usize {
+        self.0
+    }
+
+    pub fn y(&self) -> usize {
+        self.1
+    }
+
+    pub fn points_up(&self) -> bool {
+        (self.0 + self.1) % 2 == 0
+    }
+}
+
+impl GridCoord for Tri {}
+
+impl From<(usize, usize)> for Tri {
+    fn from(x: (usize, usize)) -> Self {
+        Tri(x.0, x.1)
+    }
+}
+
+impl From<Tri> for (usize, usize) {
+    fn from(x: Tri) -> (usize, usize) {
+        (x.0, x.1)
+    }
+}
+
+
+/// Signed coordinates for grids without bounds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point(pub isize, pub isize);
+
+impl Point {
+    pub fn x(&self) -> isize {
+        self.0
+    }
+
+    pub fn y(&self) -> isize {
+        self.1
+    }
+
+    /// The coordinates displaced by the given deltas.
+    pub fn offset(&self, dx: isize, dy: isize) -> Self {
+        Point(self.0 + dx, self.1 + dy)
+    }
+}
+
+impl GridCoord for Point {}
+
+impl From<(isize, isize)> for Point {
+    fn from(x: (isize, isize)) -> Self {
+        Point(x.0, x.1)
+    }
+}
+
+impl<'a> From<&'a (isize, isize)> for Point {
+    fn from(x: &(isize, isize)) -> Self {
+        Point(x.0, x.1)
+    }
+}
+
+impl From<Point> for (isize, isize) {
+    fn from(x: Point) -> (isize, isize) {
+        (x.0, x.1)
+    }
+}
+
+impl From<Dim2> for Point {
+    fn from(x: Dim2) -> Point {
+        Point(x.0 as isize, x.1 as isize)
+    }
+}
@@ -0,0 +1,321 @@
+//! Life 1.05 and Life 1.06 patterns.
+//!
+//! Life 1.05 draws blocks of cells positioned by `#P x y` lines:
+//!
+//! ```ignore
+//! #Life 1.05
+//! #D Glider
+//! #N
+//! #P -1 -1
+//! .*.
+//! ..*
+//! ***
+//! ```
+//!
+//! Life 1.06 lists the `x y` coordinates of every alive cell:
+//!
+//! ```ignore
+//! #Life 1.06
+//! 0 -1
+//! 1 0
+//! -1 1
+//! 0 1
+//! 1 1
+//! ```
+//!
+//! In both formats `x` is the column and `y` the row.
+
+use itertools::Itertools;
+
+use cell::Cell;
+use coord::Point;
+use grid::Grid;
+use rule::Rule;
+use super::{ Document, Error, ErrorKind, alive, bounds, crop, extend };
+
+
+/// Maximum line length of a Life 1.05 block.
+const WIDTH: usize = 80;
+
+
+pub fn parse_105(s: &str) -> Result<Document, Error> {
+    let mut points = vec![];
+    let mut area = None;
+    let mut comments = vec![];
+    let mut rule = None;
+    let mut origin = Point(0, 0);
+    let mut row = 0;
+
+    for (i, line) in s.lines().enumerate() {
+        let n = i + 1;
+        let line = line.trim_end();
+
+        if line.starts_with("#Life") {
+            continue;
+        }
+
+        if line.starts_with('#') {
+            let text = line.chars().skip(2).collect::<String>().trim().to_string();
+
+            match line.chars().nth(1) {
+                Some('D') => comments.push(text),
+                Some('N') => rule = Some(Rule::conway()),
+                Some('R') => {
+                    let x = text.parse().map_err(|e| Error::new(n, 4, ErrorKind::Rule(e)))?;
+                    rule = Some(x);
+                }
+                Some('P') => {
+                    let (x, y) = coordinates(&line[2..], n, 3)?;
+                    origin = Point(y, x);
+                    row = 0;
+                }
+                _ => {}
+            }
+
+            continue;
+        }
+
+        for (j, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                '*' => {
+                    let point = origin.offset(row, j as isize);
+                    extend(&mut area, point, n, j + 1)?;
+                    points.push(point);
+                }
+                c => return Err(Error::new(n, j + 1, ErrorKind::Unexpected(c))),
+            }
+        }
+
+        row += 1;
+    }
+
+    let mut doc = Document::new(crop(&points));
+    doc.comments = comments;
+    doc.rule = rule;
+
+    Ok(doc)
+}
+
+
+pub fn parse_106(s: &str) -> Result<Document, Error> {
+    let mut points = vec![];
+    let mut area = None;
+
+    for (i, line) in s.lines().enumerate() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        let (x, y) = coordinates(line, i + 1, 1)?;
+        extend(&mut area, Point(y, x), i + 1, 1)?;
+        points.push(Point(y, x));
+    }
+
+    Ok(Document::new(crop(&points)))
+}
+
+
+/// Writes the alive cells of the grid as a single block, split every 80
+/// columns.
+pub fn write_105<G, C>(grid: G) -> String
+    where G: Grid<Cell = Cell> + IntoIterator<Item = (C, Cell)>,
+          C: Into<Point> {
+    let rule = grid.rule();
+    let points = alive(grid);
+    let mut res = String::from("#Life 1.05\n");
+
+    // Life 1.05 writes rules survival first, without letters or states.
+    // Other rules are written as they are.
+    if rule == Rule::conway() {
+        res.push_str("#N\n");
+    } else if rule.is_totalistic() && rule.states() == 2 {
+        let digits = |xs: Vec<usize>| xs.iter().map(|x| x.to_string()).collect::<String>();
+        res.push_str(&format!("#R {}/{}\n", digits(rule.survival()), digits(rule.birth())));
+    } else {
+        res.push_str(&format!("#R {}\n", rule));
+    }
+
+    let (min, max) = match bounds(points.iter().cloned()) {
+        Some(x) => x,
+        None => return res,
+    };
+
+    let rows = (max.0 - min.0 + 1) as usize;
+    let cols = (max.1 - min.1 + 1) as usize;
+    let mut canvas = vec![vec!['.'; cols]; rows];
+
+    for point in &points {
+        canvas[(point.0 - min.0) as usize][(point.1 - min.1) as usize] = '*';
+    }
+
+    for start in (0..cols).step_by(WIDTH) {
+        let end = (start + WIDTH).min(cols);
+        res.push_str(&format!("#P {} {}\n", min.1 + start as isize, min.0));
+
+        for row in &canvas {
+            let block = &row[start..end];
+            let len = block.iter().rposition(|&x| x == '*').map_or(1, |x| x + 1);
+            res.push_str(&format!("{}\n", block[..len].iter().collect::<String>()));
+        }
+    }
+
+    res
+}
+
+
+/// Writes the coordinates of every alive cell of the grid.
+pub fn write_106<G, C>(grid: G) -> String
+    where G: Grid<Cell = Cell> + IntoIterator<Item = (C, Cell)>,
+          C: Into<Point> {
+    let lines = alive(grid).into_iter()
+        .sorted()
+        .into_iter()
+        .map(|x| format!("{} {}\n", x.1, x.0))
+        .collect::<String>();
+
+    format!("#Life 1.06\n{}", lines)
+}
+
+
+/// Reads a pair of integers separated by whitespace. `column` is where the
+/// given text starts in its line.
+fn coordinates(s: &str, n: usize, column: usize) -> Result<(isize, isize), Error> {
+    let mut xs = vec![];
+    let mut start = None;
+
+    for (i, c) in s.chars().chain(Some(' ')).enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(j)) => {
+                xs.push((j, s.chars().skip(j).take(i - j).collect::<String>()));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    let end = column + s.chars().count();
+
+    if xs.len() != 2 {
+        let (col, c) = match xs.get(2) {
+            Some(&(j, ref x)) => (column + j, x.chars().next().unwrap()),
+            None => (end, '\n'),
+        };
+
+        return Err(Error::new(n, col, ErrorKind::Unexpected(c)));
+    }
+
+    let number = |&(j, ref x): &(usize, String)| {
+        x.parse::<isize>().map_err(|_| {
+            let (k, c) = x.chars()
+                .enumerate()
+                .find(|&(k, c)| !(c.is_digit(10) || (k == 0 && c == '-')))
+                .unwrap_or((0, x.chars().next().unwrap()));
+
+            Error::new(n, column + j + k, ErrorKind::Unexpected(c))
+        })
+    };
+
+    Ok((number(&xs[0])?, number(&xs[1])?))
+}
+
+
+#[test]
+fn test_parse_105() {
+    let s = "#Life 1.05\n\
+             #D Two blocks\n\
+             #R 23/36\n\
+             #P -3 -1\n\
+             **\n\
+             **\n\
+             #P 2 0\n\
+             **\n\
+             **\n";
+    let doc = parse_105(s).unwrap();
+    let cells = doc.pattern.0;
+
+    assert_eq!(doc.comments, vec!["Two blocks".to_string()]);
+    assert_eq!(doc.rule, Some(Rule::new(&[3, 6], &[2, 3])));
+    assert_eq!(cells.dim(), (3, 7));
+    assert_eq!(cells[[0, 0]], Cell::Alive);
+    assert_eq!(cells[[2, 6]], Cell::Alive);
+    assert_eq!(cells[[2, 0]], Cell::Unborn);
+}
+
+#[test]
+fn test_parse_106_errors() {
+    let xs = vec![
+        ("#Life 1.06\n0 -1\n1 x\n", (3, 3, 'x')),
+        ("#Life 1.06\n0 -1\n1 2-\n", (3, 4, '-')),
+        ("#Life 1.06\n0 -1\n1\n", (3, 2, '\n')),
+        ("#Life 1.06\n0 -1 3\n", (2, 6, '3')),
+    ];
+
+    for (s, (line, column, c)) in xs {
+        let err = parse_106(s).unwrap_err();
+        assert_eq!((err.line, err.column, err.kind), (line, column, ErrorKind::Unexpected(c)));
+    }
+}
+
+#[test]
+fn test_parse_too_large() {
+    let err = parse_106("#Life 1.06\n0 0\n3000000000 3000000000\n1 1\n").unwrap_err();
+    assert_eq!((err.line, err.column), (3, 1));
+    assert_eq!(err.kind, ErrorKind::TooLarge(3000000001, 3000000001));
+
+    let err = parse_105("#Life 1.05\n#P 0 0\n*\n#P 5000 5000\n.*\n#P 1 1\n*\n").unwrap_err();
+    assert_eq!((err.line, err.column), (5, 2));
+    assert_eq!(err.kind, ErrorKind::TooLarge(5001, 5002));
+
+    let err = "#Life 1.06\n0 0\n3000000000 3000000000\n".parse::<Document>().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::TooLarge(3000000001, 3000000001));
+}
+
+#[test]
+fn test_write() {
+    use community::Community;
+    use pattern::{ Glider, Layout, Pattern };
+
+    let mut grid = Community::empty(8).with_rule("B36/S23".parse().unwrap());
+    grid.insert(Layout::new((3, 2), Glider::BottomRight));
+
+    assert_eq!(write_105(grid.clone()), "#Life 1.05\n#R 23/36\n#P 2 3\n.*\n..*\n***\n");
+    assert_eq!(write_106(grid.clone()), "#Life 1.06\n3 3\n4 4\n2 5\n3 5\n4 5\n");
+
+    for s in vec![write_105(grid.clone()), write_106(grid)] {
+        let doc: Document = s.parse().unwrap();
+        assert_eq!(doc.pattern.0, Glider::BottomRight.pattern());
+    }
+}
+
+#[test]
+fn test_write_105_wide() {
+    use population::Population;
+
+    let mut grid = Population::empty(100);
+    grid.regenerate((10, 0));
+    grid.regenerate((11, 99));
+
+    let s = write_105(grid);
+
+    assert!(s.lines().all(|x| x.len() <= WIDTH));
+    assert_eq!(s.matches("#P").count(), 2);
+    assert_eq!(parse_105(&s).unwrap().pattern.0.dim(), (2, 100));
+}
+
+#[test]
+fn test_write_105_rule() {
+    use population::Population;
+
+    for rule in vec!["B2/S/C3", "B2-a/S12"] {
+        let rule: Rule = rule.parse().unwrap();
+        let mut grid = Population::empty(4).with_rule(rule);
+        grid.regenerate((1, 1));
+
+        let s = write_105(grid);
+
+        assert!(s.contains(&format!("#R {}\n", rule)), "{}", s);
+        assert_eq!(parse_105(&s).unwrap().rule, Some(rule));
+    }
+}
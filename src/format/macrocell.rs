@@ -0,0 +1,504 @@
+//! Macrocell (`.mc`) patterns.
+//!
+//! ```ignore
+//! [M2] (golly 2.0)
+//! #R B3/S23
+//! .*$..*$***$
+//! 4 0 0 0 1
+//! ```
+//!
+//! The pattern is a quadtree written bottom up. Leaves are 8 x 8 squares
+//! drawn with `.`, `*` and `$`, other lines are `level nw ne sw se` where
+//! each child is the 1-based line number of a previous node, or 0 when
+//! empty. The last node is the root.
+//!
+//! Only two state patterns are supported.
+
+use std::collections::HashMap;
+use std::fmt;
+use ndarray::prelude::*;
+
+use cell::Cell;
+use coord::Point;
+use grid::Grid;
+use pattern::Custom;
+use rule::Rule;
+use super::{ Document, Error, ErrorKind, LIMIT, alive, bounds };
+
+
+/// Deepest supported tree, so coordinates fit in an `isize`.
+const MAX_LEVEL: u8 = 62;
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Node {
+    /// 8 x 8 square, one byte per row with the first column in the lowest
+    /// bit.
+    Leaf([u8; 8]),
+    /// Square of `2^level` cells. Children are 1-based node numbers, 0 for
+    /// empty squares.
+    Branch {
+        level: u8,
+        nw: usize,
+        ne: usize,
+        sw: usize,
+        se: usize,
+    },
+}
+
+impl Node {
+    pub fn level(&self) -> u8 {
+        match *self {
+            Node::Leaf(_) => 3,
+            Node::Branch { level, .. } => level,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Macrocell {
+    pub rule: Option<Rule>,
+    pub generation: Option<usize>,
+    pub comments: Vec<String>,
+    pub nodes: Vec<Node>,
+}
+
+impl Macrocell {
+    pub fn new() -> Self {
+        Macrocell::default()
+    }
+
+    /// Level of the root, the pattern is `2^level` cells wide.
+    pub fn level(&self) -> u8 {
+        self.nodes.last().map_or(3, |x| x.level())
+    }
+
+    /// The smallest rectangle holding every alive cell, relative to the top
+    /// left corner of the root.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let mut memo = HashMap::new();
+
+        match self.nodes.len() {
+            0 => None,
+            n => self.node_bounds(n, &mut memo),
+        }
+    }
+
+    /// Draws the alive cells into a dense pattern, cropped to their bounds,
+    /// as long as it holds at most `limit` cells.
+    pub fn expand(&self, limit: usize) -> Result<Custom, Error> {
+        let (min, max) = match self.bounds() {
+            Some(x) => x,
+            None => return Ok(Custom(Array2::from_elem((0, 0), Cell::Unborn))),
+        };
+
+        let rows = (max.0 - min.0 + 1) as usize;
+        let cols = (max.1 - min.1 + 1) as usize;
+
+        if rows.checked_mul(cols).map_or(true, |x| x > limit) {
+            return Err(Error::new(self.nodes.len(), 1, ErrorKind::TooLarge(rows, cols)));
+        }
+
+        let mut cells = Array2::from_elem((rows, cols), Cell::Unborn);
+        self.paint(self.nodes.len(), Point(-min.0, -min.1), &mut cells);
+
+        Ok(Custom(cells))
+    }
+
+    /// Adds a node, reusing an identical one if any, and returns its number.
+    fn intern(&mut self, node: Node, index: &mut HashMap<Node, usize>) -> usize {
+        if let Some(&x) = index.get(&node) {
+            return x;
+        }
+
+        self.nodes.push(node);
+        index.insert(node, self.nodes.len());
+
+        self.nodes.len()
+    }
+
+    fn node_bounds(&self, n: usize, memo: &mut HashMap<usize, Option<(Point, Point)>>)
+        -> Option<(Point, Point)> {
+        if n == 0 {
+            return None;
+        }
+
+        if let Some(&x) = memo.get(&n) {
+            return x;
+        }
+
+        let res = match self.nodes[n - 1] {
+            Node::Leaf(rows) => {
+                let points = iproduct!(0..8, 0..8)
+                    .filter(|&(x, y)| rows[x] & (1 << y) != 0)
+                    .map(|(x, y)| Point(x as isize, y as isize));
+
+                bounds(points)
+            }
+            Node::Branch { level, nw, ne, sw, se } => {
+                let half = 1 << (level - 1);
+                let children = [(nw, 0, 0), (ne, 0, half), (sw, half, 0), (se, half, half)];
+                let points = children.iter()
+                    .filter_map(|&(child, dx, dy)| {
+                        self.node_bounds(child, memo)
+                            .map(|(min, max)| vec![min.offset(dx, dy), max.offset(dx, dy)])
+                    })
+                    .flat_map(|x| x)
+                    .collect::<Vec<Point>>();
+
+                bounds(points)
+            }
+        };
+
+        memo.insert(n, res);
+
+        res
+    }
+
+    fn paint(&self, n: usize, corner: Point, cells: &mut Array2<Cell>) {
+        if n == 0 {
+            return;
+        }
+
+        match self.nodes[n - 1] {
+            Node::Leaf(rows) => {
+                for (x, y) in iproduct!(0..8, 0..8) {
+                    if rows[x] & (1 << y) != 0 {
+                        let point = corner.offset(x as isize, y as isize);
+                        cells[[point.0 as usize, point.1 as usize]] = Cell::Alive;
+                    }
+                }
+            }
+            Node::Branch { level, nw, ne, sw, se } => {
+                let half = 1 << (level - 1);
+
+                self.paint(nw, corner, cells);
+                self.paint(ne, corner.offset(0, half), cells);
+                self.paint(sw, corner.offset(half, 0), cells);
+                self.paint(se, corner.offset(half, half), cells);
+            }
+        }
+    }
+}
+
+
+/// Reads the quadtree without expanding it.
+pub fn read(s: &str) -> Result<Macrocell, Error> {
+    let mut tree = Macrocell::new();
+
+    for (i, line) in s.lines().enumerate() {
+        let n = i + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with("[M2]") {
+            continue;
+        }
+
+        if line.starts_with('#') {
+            let text = line.chars().skip(2).collect::<String>().trim().to_string();
+
+            match line.chars().nth(1) {
+                Some('R') => {
+                    let x = text.parse().map_err(|e| Error::new(n, 4, ErrorKind::Rule(e)))?;
+                    tree.rule = Some(x);
+                }
+                Some('G') => {
+                    let x = text.parse()
+                        .map_err(|_| Error::new(n, 4, ErrorKind::Header("invalid generation".into())))?;
+                    tree.generation = Some(x);
+                }
+                _ => tree.comments.push(text),
+            }
+
+            continue;
+        }
+
+        let node = if line.starts_with(|c: char| c.is_digit(10)) {
+            branch(line, n, &tree.nodes)?
+        } else {
+            leaf(line, n)?
+        };
+
+        tree.nodes.push(node);
+    }
+
+    Ok(tree)
+}
+
+/// Reads and expands the pattern if it holds at most `LIMIT` cells.
+pub fn parse(s: &str) -> Result<Document, Error> {
+    let tree = read(s)?;
+    let mut doc = Document::new(tree.expand(LIMIT)?);
+    doc.rule = tree.rule;
+    doc.comments = tree.comments;
+
+    Ok(doc)
+}
+
+
+/// Builds the quadtree of every alive cell of the grid.
+///
+/// Fails if the grid has a Generations rule, as only two state patterns
+/// can be written.
+pub fn from_grid<G, C>(grid: G) -> Result<Macrocell, String>
+    where G: Grid<Cell = Cell> + IntoIterator<Item = (C, Cell)>,
+          C: Into<Point> {
+    let rule = grid.rule();
+
+    if rule.states() > 2 {
+        return Err(format!("Macrocell does not support the rule {}", rule));
+    }
+
+    let points = alive(grid);
+    let mut tree = Macrocell::new();
+    tree.rule = Some(rule);
+
+    let (min, max) = match bounds(points.iter().cloned()) {
+        Some(x) => x,
+        None => return Ok(tree),
+    };
+
+    let size = (max.0 - min.0).max(max.1 - min.1) as usize + 1;
+    let level = (0..).find(|&x| 1 << x >= size).unwrap_or(0).max(3);
+    let points = points.into_iter()
+        .map(|x| ((x.0 - min.0) as usize, (x.1 - min.1) as usize))
+        .collect();
+
+    build(&mut tree, &mut HashMap::new(), points, level);
+
+    Ok(tree)
+}
+
+/// Writes the quadtree of the grid, failing like `from_grid`.
+pub fn write<G, C>(grid: G) -> Result<String, String>
+    where G: Grid<Cell = Cell> + IntoIterator<Item = (C, Cell)>,
+          C: Into<Point> {
+    from_grid(grid).map(|x| x.to_string())
+}
+
+
+fn build(tree: &mut Macrocell, index: &mut HashMap<Node, usize>,
+         points: Vec<(usize, usize)>, level: u8) -> usize {
+    if points.is_empty() {
+        return 0;
+    }
+
+    if level == 3 {
+        let mut rows = [0; 8];
+
+        for (x, y) in points {
+            rows[x] |= 1 << y;
+        }
+
+        return tree.intern(Node::Leaf(rows), index);
+    }
+
+    let half = 1 << (level - 1);
+    let mut quadrants = vec![vec![], vec![], vec![], vec![]];
+
+    for (x, y) in points {
+        let i = (x / half) * 2 + y / half;
+        quadrants[i].push((x % half, y % half));
+    }
+
+    let mut quadrants = quadrants.into_iter();
+    let mut next = || build(tree, index, quadrants.next().unwrap(), level - 1);
+    let (nw, ne, sw, se) = (next(), next(), next(), next());
+
+    tree.intern(Node::Branch { level: level, nw: nw, ne: ne, sw: sw, se: se }, index)
+}
+
+
+fn leaf(line: &str, n: usize) -> Result<Node, Error> {
+    let mut rows = [0; 8];
+    let (mut x, mut y) = (0, 0);
+
+    for (i, c) in line.chars().enumerate() {
+        match c {
+            '$' => {
+                x += 1;
+                y = 0;
+                continue;
+            }
+            '.' | '*' if x < 8 && y < 8 => {
+                if c == '*' {
+                    rows[x] |= 1 << y;
+                }
+                y += 1;
+            }
+            '.' | '*' => return Err(Error::new(n, i + 1, ErrorKind::Overflow)),
+            c => return Err(Error::new(n, i + 1, ErrorKind::Unexpected(c))),
+        }
+    }
+
+    Ok(Node::Leaf(rows))
+}
+
+fn branch(line: &str, n: usize, nodes: &[Node]) -> Result<Node, Error> {
+    let mut xs = vec![];
+    let mut column = 1;
+
+    for part in line.split(' ') {
+        if !part.is_empty() {
+            let x = part.parse::<usize>().map_err(|_| {
+                let c = part.chars().find(|c| !c.is_digit(10)).unwrap_or(' ');
+                Error::new(n, column, ErrorKind::Unexpected(c))
+            })?;
+            xs.push((column, x));
+        }
+
+        column += part.len() + 1;
+    }
+
+    if xs.len() != 5 {
+        return Err(Error::new(n, column - 1, ErrorKind::Unexpected('\n')));
+    }
+
+    let level = xs[0].1;
+
+    if level <= 3 || level > MAX_LEVEL as usize {
+        let msg = format!("nodes of level {}", level);
+        return Err(Error::new(n, 1, ErrorKind::Unsupported(msg)));
+    }
+
+    for &(col, child) in &xs[1..] {
+        let defined = child == 0 ||
+                      nodes.get(child - 1).map_or(false, |x| x.level() as usize == level - 1);
+
+        if !defined {
+            return Err(Error::new(n, col, ErrorKind::Reference(child)));
+        }
+    }
+
+    Ok(Node::Branch {
+        level: level as u8,
+        nw: xs[1].1,
+        ne: xs[2].1,
+        sw: xs[3].1,
+        se: xs[4].1,
+    })
+}
+
+
+impl fmt::Display for Macrocell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[M2] (conway)")?;
+
+        if let Some(rule) = self.rule {
+            writeln!(f, "#R {}", rule)?;
+        }
+
+        if let Some(gen) = self.generation {
+            writeln!(f, "#G {}", gen)?;
+        }
+
+        for comment in &self.comments {
+            writeln!(f, "#C {}", comment)?;
+        }
+
+        for node in &self.nodes {
+            match *node {
+                Node::Leaf(rows) => {
+                    let len = rows.iter().rposition(|&x| x != 0).map_or(0, |x| x + 1);
+                    let line = rows[..len].iter()
+                        .map(|&row| {
+                            let width = 8 - row.leading_zeros() as usize;
+                            let cells = (0..width)
+                                .map(|y| if row & (1 << y) != 0 { '*' } else { '.' })
+                                .collect::<String>();
+
+                            format!("{}$", cells)
+                        })
+                        .collect::<String>();
+
+                    writeln!(f, "{}", line)?;
+                }
+                Node::Branch { level, nw, ne, sw, se } => {
+                    writeln!(f, "{} {} {} {} {}", level, nw, ne, sw, se)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+const GLIDER: &'static str = "[M2] (golly 2.0)\n#R B3/S23\n.*$..*$***$\n4 0 0 0 1\n";
+
+/// Two cells in opposite corners of a `2^level` square.
+#[cfg(test)]
+fn diagonal(level: u8) -> String {
+    let mut s = String::from("[M2] (golly 2.0)\n*$\n4 1 0 0 1\n");
+
+    for (i, x) in (5..level + 1).enumerate() {
+        s.push_str(&format!("{} {} 0 0 {}\n", x, i + 2, i + 2));
+    }
+
+    s
+}
+
+#[test]
+fn test_parse() {
+    use pattern::{ Glider, Pattern };
+
+    let tree = read(GLIDER).unwrap();
+
+    assert_eq!(tree.level(), 4);
+    assert_eq!(tree.rule, Some(Rule::conway()));
+    assert_eq!(tree.bounds(), Some((Point(8, 8), Point(10, 10))));
+    assert_eq!(parse(GLIDER).unwrap().pattern.0, Glider::BottomRight.pattern());
+}
+
+#[test]
+fn test_too_large() {
+    let tree = read(&diagonal(20)).unwrap();
+    let side = (1 << 20) - 7;
+
+    assert_eq!(tree.nodes.len(), 18);
+    assert_eq!(tree.bounds(), Some((Point(0, 0), Point(side - 1, side - 1))));
+
+    let err = tree.expand(LIMIT).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::TooLarge(side as usize, side as usize));
+    assert_eq!(read(&diagonal(6)).unwrap().expand(LIMIT).unwrap().0.dim(), (57, 57));
+}
+
+#[test]
+fn test_read_errors() {
+    let xs = vec![
+        ("[M2]\n.*$..*$***$\n4 0 0 0 2\n", (3, 9, ErrorKind::Reference(2))),
+        ("[M2]\n.*$..*$***$\n5 0 1 0 0\n", (3, 5, ErrorKind::Reference(1))),
+        ("[M2]\n.*$..*$***$\n4 0 0 x 1\n", (3, 7, ErrorKind::Unexpected('x'))),
+        ("[M2]\n.*$..*$***$\n4 0 0 1\n", (3, 8, ErrorKind::Unexpected('\n'))),
+        ("[M2]\n.*$..*$*****.***$\n", (2, 16, ErrorKind::Overflow)),
+        ("[M2]\n.*$..*$**o$\n", (2, 10, ErrorKind::Unexpected('o'))),
+        ("[M2]\n1 0 1 1 0\n", (2, 1, ErrorKind::Unsupported("nodes of level 1".into()))),
+    ];
+
+    for (s, (line, column, kind)) in xs {
+        let err = read(s).unwrap_err();
+        assert_eq!((err.line, err.column, err.kind), (line, column, kind), "{:?}", s);
+    }
+}
+
+#[test]
+fn test_write() {
+    use community::Community;
+    use pattern::{ Layout, Pulsar };
+
+    let mut grid = Community::empty(40);
+    grid.insert(Layout::new((3, 20), Pulsar));
+
+    let s = write(grid.clone()).unwrap();
+    let mut copy = Community::empty(40);
+    copy.insert(Layout::new((4, 21), parse(&s).unwrap().pattern));
+
+    assert!(s.starts_with("[M2] (conway)\n#R B3/S23\n"));
+    assert_eq!(write(copy), Ok(s));
+    assert_eq!(write(Community::empty(4)), Ok("[M2] (conway)\n#R B3/S23\n".to_string()));
+
+    let generations = Community::empty(4).with_rule("B2/S/C3".parse().unwrap());
+    assert!(write(generations).is_err());
+}
@@ -0,0 +1,283 @@
+//! Pattern file formats.
+//!
+//! Each format module reads a `Document` and writes any grid. `parse` detects
+//! the format from the content.
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+use ndarray::prelude::*;
+
+use cell::Cell;
+use coord::Point;
+use grid::Grid;
+use pattern::Custom;
+use rule::Rule;
+
+pub mod rle;
+pub mod plaintext;
+pub mod life;
+pub mod macrocell;
+
+
+/// Maximum number of cells a pattern read from a file can hold.
+pub const LIMIT: usize = 1 << 22;
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Rle,
+    Plaintext,
+    Life105,
+    Life106,
+    Macrocell,
+}
+
+/// Guesses the format from the first non blank line.
+pub fn sniff(s: &str) -> Option<Format> {
+    let line = match s.lines().map(|x| x.trim()).find(|x| !x.is_empty()) {
+        Some(x) => x,
+        None => return None,
+    };
+
+    if line.starts_with("[M2]") {
+        Some(Format::Macrocell)
+    } else if line.starts_with("#Life 1.06") {
+        Some(Format::Life106)
+    } else if line.starts_with("#Life 1.05") {
+        Some(Format::Life105)
+    } else if line.starts_with('!') || line.chars().all(|c| c == '.' || c == 'O' || c == '*') {
+        Some(Format::Plaintext)
+    } else if line.starts_with('#') || (line.starts_with('x') && line.contains('=')) {
+        Some(Format::Rle)
+    } else {
+        None
+    }
+}
+
+/// Reads a pattern in any supported format.
+pub fn parse(s: &str) -> Result<Document, Error> {
+    match sniff(s) {
+        Some(Format::Rle) => rle::parse(s),
+        Some(Format::Plaintext) => plaintext::parse(s),
+        Some(Format::Life105) => life::parse_105(s),
+        Some(Format::Life106) => life::parse_106(s),
+        Some(Format::Macrocell) => macrocell::parse(s),
+        None => Err(Error::new(1, 1, ErrorKind::Format)),
+    }
+}
+
+
+/// A pattern read from a file together with its metadata.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<Rule>,
+    pub pattern: Custom,
+}
+
+impl Document {
+    fn new(pattern: Custom) -> Self {
+        Document {
+            name: None,
+            author: None,
+            comments: vec![],
+            rule: None,
+            pattern: pattern,
+        }
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Document, Error> {
+        parse(s)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The header is missing or malformed.
+    Header(String),
+    /// The rule can't be parsed.
+    Rule(String),
+    /// A character not allowed at this position.
+    Unexpected(char),
+    /// Cells placed beyond the size declared in the header.
+    Overflow,
+    /// The format can't be recognised.
+    Format,
+    /// A reference to an undefined node, or one of the wrong level.
+    Reference(usize),
+    /// A feature of the format this crate can't handle.
+    Unsupported(String),
+    /// The pattern has too many rows and columns to be expanded.
+    TooLarge(usize, usize),
+}
+
+/// Malformed input, with the 1-based line and column where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new(line: usize, column: usize, kind: ErrorKind) -> Self {
+        Error {
+            line: line,
+            column: column,
+            kind: kind,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+
+        match self.kind {
+            ErrorKind::Header(ref x) => write!(f, "invalid header, {}", x),
+            ErrorKind::Rule(ref x) => write!(f, "{}", x),
+            ErrorKind::Unexpected(x) => write!(f, "unexpected {:?}", x),
+            ErrorKind::Overflow => write!(f, "cells beyond the pattern size"),
+            ErrorKind::Format => write!(f, "unknown format"),
+            ErrorKind::Reference(x) => write!(f, "invalid node reference {}", x),
+            ErrorKind::Unsupported(ref x) => write!(f, "unsupported {}", x),
+            ErrorKind::TooLarge(x, y) => write!(f, "pattern of {}x{} cells is too large", x, y),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match self.kind {
+            ErrorKind::Header(_) => "invalid header",
+            ErrorKind::Rule(_) => "invalid rule",
+            ErrorKind::Unexpected(_) => "unexpected character",
+            ErrorKind::Overflow => "cells beyond the pattern size",
+            ErrorKind::Format => "unknown format",
+            ErrorKind::Reference(_) => "invalid node reference",
+            ErrorKind::Unsupported(_) => "unsupported feature",
+            ErrorKind::TooLarge(..) => "pattern too large",
+        }
+    }
+}
+
+
+/// The smallest rectangle holding every point, as its top left and bottom
+/// right corners (inclusive).
+fn bounds<I: IntoIterator<Item = Point>>(points: I) -> Option<(Point, Point)> {
+    let mut points = points.into_iter();
+    let first = match points.next() {
+        Some(x) => x,
+        None => return None,
+    };
+
+    Some(points.fold((first, first), |(min, max), c| {
+        (Point(min.0.min(c.0), min.1.min(c.1)),
+         Point(max.0.max(c.0), max.1.max(c.1)))
+    }))
+}
+
+/// The alive cells of the grid.
+fn alive<G, C>(grid: G) -> Vec<Point>
+    where G: Grid<Cell = Cell> + IntoIterator<Item = (C, Cell)>,
+          C: Into<Point> {
+    grid.into_iter()
+        .filter(|&(_, cell)| cell == Cell::Alive)
+        .map(|(coord, _)| coord.into())
+        .collect()
+}
+
+/// Rows and columns of the rectangle between two corners. The span of two
+/// `isize` always fits in a `usize`.
+fn size(min: Point, max: Point) -> (usize, usize) {
+    ((max.0.wrapping_sub(min.0) as usize).saturating_add(1),
+     (max.1.wrapping_sub(min.1) as usize).saturating_add(1))
+}
+
+/// Grows the bounds to hold the point, as long as they hold at most `LIMIT`
+/// cells. Errors are reported at `line` and `column`.
+fn extend(bounds: &mut Option<(Point, Point)>, point: Point, line: usize, column: usize)
+          -> Result<(), Error> {
+    let (min, max) = match *bounds {
+        Some((min, max)) => {
+            (Point(min.0.min(point.0), min.1.min(point.1)),
+             Point(max.0.max(point.0), max.1.max(point.1)))
+        }
+        None => (point, point),
+    };
+
+    let (rows, cols) = size(min, max);
+
+    if rows.checked_mul(cols).map_or(true, |x| x > LIMIT) {
+        return Err(Error::new(line, column, ErrorKind::TooLarge(rows, cols)));
+    }
+
+    *bounds = Some((min, max));
+
+    Ok(())
+}
+
+/// A pattern holding the given alive cells, cropped to their bounds, which
+/// must have been checked with `extend`.
+fn crop(points: &[Point]) -> Custom {
+    let (min, max) = match bounds(points.iter().cloned()) {
+        Some(x) => x,
+        None => return Custom(Array2::from_elem((0, 0), Cell::Unborn)),
+    };
+
+    let mut cells = Array2::from_elem(size(min, max), Cell::Unborn);
+
+    for point in points {
+        cells[[(point.0 - min.0) as usize, (point.1 - min.1) as usize]] = Cell::Alive;
+    }
+
+    Custom(cells)
+}
+
+
+#[test]
+fn test_sniff() {
+    let xs = vec![
+        ("#N Glider\nx = 3, y = 3\nbo$2bo$3o!", Some(Format::Rle)),
+        ("x = 3, y = 3\nbo$2bo$3o!", Some(Format::Rle)),
+        ("!Name: Glider\n.O.\n..O\nOOO", Some(Format::Plaintext)),
+        ("\n.O.\n..O\nOOO", Some(Format::Plaintext)),
+        ("#Life 1.05\n#P -1 -1\n.*.\n..*\n***", Some(Format::Life105)),
+        ("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1", Some(Format::Life106)),
+        ("[M2] (golly 2.0)\n.*$..*$***$\n4 0 0 0 1", Some(Format::Macrocell)),
+        ("glider", None),
+        ("", None),
+    ];
+
+    for (s, expected) in xs {
+        assert_eq!(sniff(s), expected, "{:?}", s);
+    }
+}
+
+#[test]
+fn test_parse_any() {
+    use pattern::{ Glider, Pattern };
+
+    let xs = vec![
+        "#N Glider\nx = 3, y = 3\nbo$2bo$3o!",
+        "!Name: Glider\n.O.\n..O\nOOO",
+        "#Life 1.05\n#P -1 -1\n.*.\n..*\n***",
+        "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1",
+        "[M2] (golly 2.0)\n.*$..*$***$\n4 0 0 0 1",
+    ];
+
+    for s in xs {
+        let doc: Document = s.parse().unwrap();
+        assert_eq!(doc.pattern.0, Glider::BottomRight.pattern(), "{:?}", s);
+    }
+
+    assert_eq!("glider".parse::<Document>().unwrap_err().kind, ErrorKind::Format);
+}
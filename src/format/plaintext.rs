@@ -0,0 +1,154 @@
+//! Plaintext (`.cells`) patterns.
+//!
+//! ```ignore
+//! !Name: Glider
+//! !The smallest spaceship.
+//! .O.
+//! ..O
+//! OOO
+//! ```
+
+use ndarray::prelude::*;
+
+use cell::Cell;
+use coord::Point;
+use grid::Grid;
+use pattern::Custom;
+use super::{ Document, Error, ErrorKind, alive, bounds };
+
+
+pub fn parse(s: &str) -> Result<Document, Error> {
+    let mut doc = Document::new(Custom(Array2::from_elem((0, 0), Cell::Unborn)));
+    let mut rows: Vec<Vec<Cell>> = vec![];
+
+    for (i, line) in s.lines().enumerate() {
+        let n = i + 1;
+        let line = line.trim_end();
+
+        if line.starts_with('!') {
+            let text = line[1..].trim();
+
+            if text.starts_with("Name:") {
+                doc.name = Some(text["Name:".len()..].trim().to_string());
+            } else if text.starts_with("Author:") {
+                doc.author = Some(text["Author:".len()..].trim().to_string());
+            } else {
+                doc.comments.push(text.to_string());
+            }
+
+            continue;
+        }
+
+        // Blank lines are empty rows once the pattern has started.
+        if line.is_empty() && rows.is_empty() {
+            continue;
+        }
+
+        let mut row = vec![];
+
+        for (j, c) in line.chars().enumerate() {
+            match c {
+                '.' => row.push(Cell::Unborn),
+                'O' | '*' => row.push(Cell::Alive),
+                c => return Err(Error::new(n, j + 1, ErrorKind::Unexpected(c))),
+            }
+        }
+
+        rows.push(row);
+    }
+
+    let width = rows.iter().map(|x| x.len()).max().unwrap_or(0);
+    let mut cells = Array2::from_elem((rows.len(), width), Cell::Unborn);
+
+    for (x, row) in rows.iter().enumerate() {
+        for (y, &cell) in row.iter().enumerate() {
+            cells[[x, y]] = cell;
+        }
+    }
+
+    doc.pattern = Custom(cells);
+
+    Ok(doc)
+}
+
+
+/// Draws every alive cell of the grid. An empty grid is drawn as a single
+/// dead cell so it can still be read back.
+pub fn write<G, C>(grid: G) -> String
+    where G: Grid<Cell = Cell> + IntoIterator<Item = (C, Cell)>,
+          C: Into<Point> {
+    let points = alive(grid);
+    let (min, max) = match bounds(points.iter().cloned()) {
+        Some(x) => x,
+        None => return ".\n".into(),
+    };
+
+    let rows = (max.0 - min.0 + 1) as usize;
+    let cols = (max.1 - min.1 + 1) as usize;
+    let mut canvas = vec![vec!['.'; cols]; rows];
+
+    for point in points {
+        canvas[(point.0 - min.0) as usize][(point.1 - min.1) as usize] = 'O';
+    }
+
+    canvas.into_iter()
+        .map(|row| {
+            let len = row.iter().rposition(|&x| x == 'O').map_or(0, |x| x + 1);
+            format!("{}\n", row[..len].iter().collect::<String>())
+        })
+        .collect()
+}
+
+
+#[test]
+fn test_parse() {
+    let s = "!Name: Beehive\n\
+             !Author: John Conway\n\
+             !The most common still life.\n\
+             .OO\n\
+             O..O\n\
+             \n\
+             .OO.\n";
+    let doc = parse(s).unwrap();
+    let cells = doc.pattern.0;
+
+    assert_eq!(doc.name, Some("Beehive".into()));
+    assert_eq!(doc.author, Some("John Conway".into()));
+    assert_eq!(doc.comments, vec!["The most common still life.".to_string()]);
+    assert_eq!(cells.dim(), (4, 4));
+    assert_eq!(cells.iter().filter(|&&x| x == Cell::Alive).count(), 6);
+    assert_eq!(cells[[2, 0]], Cell::Unborn);
+    assert_eq!(cells[[3, 2]], Cell::Alive);
+}
+
+#[test]
+fn test_parse_error() {
+    let err = parse("!Name: Blinker\nOOO\n.o.").unwrap_err();
+
+    assert_eq!((err.line, err.column), (3, 2));
+    assert_eq!(err.kind, ErrorKind::Unexpected('o'));
+}
+
+#[test]
+fn test_write() {
+    use community::Community;
+    use pattern::{ Layout, Loaf };
+    use population::Population;
+
+    let mut grid = Community::empty(8);
+    grid.insert(Layout::new((3, 2), Loaf));
+
+    let s = write(grid);
+    assert_eq!(s, ".OO\nO..O\n.O.O\n..O\n");
+
+    let mut copy = Community::empty(8);
+    copy.insert(Layout::new((3, 2), parse(&s).unwrap().pattern));
+    assert_eq!(write(copy), s);
+
+    let s = write(Population::empty(4));
+    assert_eq!(s, ".\n");
+
+    let mut copy = Population::empty(4);
+    copy.insert(Layout::new((0, 0), super::parse(&s).unwrap().pattern));
+    assert_eq!(write(copy), s);
+}
@@ -0,0 +1,467 @@
+//! Run Length Encoded patterns.
+//!
+//! ```ignore
+//! #N Glider
+//! #C The smallest spaceship.
+//! x = 3, y = 3, rule = B3/S23
+//! bob$2bo$3o!
+//! ```
+//!
+//! Two state patterns use `b` for dead and `o` for alive cells. Multi state
+//! patterns use `.` for empty cells and `A` to `X`, optionally prefixed by
+//! `p` to `y`, for states 1 to 255. States above 1 are dying cells.
+
+use ndarray::prelude::*;
+
+use cell::Cell;
+use coord::Point;
+use grid::Grid;
+use pattern::Custom;
+use rule::Rule;
+use super::{ Document, Error, ErrorKind, LIMIT, bounds };
+
+
+/// Maximum line length of the encoded pattern.
+const WIDTH: usize = 70;
+
+
+pub fn parse(s: &str) -> Result<Document, Error> {
+    let mut doc = Document::new(Custom(Array2::from_elem((0, 0), Cell::Unborn)));
+    let mut reader: Option<Reader> = None;
+    let mut lines = 0;
+
+    for (i, line) in s.lines().enumerate() {
+        let n = i + 1;
+        lines = n;
+
+        match reader {
+            Some(ref mut reader) => {
+                reader.read(line, n)?;
+
+                if reader.done {
+                    break;
+                }
+            }
+            None => {
+                if line.starts_with('#') {
+                    comment(&mut doc, line, n)?;
+                } else if !line.trim().is_empty() {
+                    let (size, rule) = header(line, n)?;
+                    doc.rule = rule.or(doc.rule);
+                    reader = Some(Reader::new(size));
+                }
+            }
+        }
+    }
+
+    match reader {
+        Some(reader) => {
+            doc.pattern = Custom(reader.cells);
+            Ok(doc)
+        }
+        None => Err(Error::new(lines + 1, 1, ErrorKind::Header("missing".into()))),
+    }
+}
+
+
+/// Encodes every alive and dying cell of the grid.
+pub fn write<G, C>(grid: G) -> String
+    where G: Grid<Cell = Cell> + IntoIterator<Item = (C, Cell)>,
+          C: Into<Point> {
+    let rule = grid.rule();
+    let cells: Vec<(Point, usize)> = grid.into_iter()
+        .map(|(coord, cell)| (coord.into(), state(cell, &rule)))
+        .filter(|&(_, x)| x > 0)
+        .collect();
+
+    encode(&cells, &rule)
+}
+
+
+fn comment(doc: &mut Document, line: &str, n: usize) -> Result<(), Error> {
+    let text = line.chars().skip(2).collect::<String>().trim().to_string();
+
+    match line.chars().nth(1) {
+        Some('N') => doc.name = Some(text),
+        Some('O') => doc.author = Some(text),
+        Some('C') | Some('c') => doc.comments.push(text),
+        Some('r') => {
+            let rule = text.parse().map_err(|e| Error::new(n, 4, ErrorKind::Rule(e)))?;
+            doc.rule = Some(rule);
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+
+/// Reads `x = 3, y = 3, rule = B3/S23` into (rows, columns) and rule.
+fn header(line: &str, n: usize) -> Result<((usize, usize), Option<Rule>), Error> {
+    let mut rows = None;
+    let mut cols = None;
+    let mut rule = None;
+    let mut column = 1;
+
+    for part in line.split(',') {
+        let col = column + part.len() - part.trim_start().len();
+        column += part.len() + 1;
+
+        let mut kv = part.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim();
+        let value = match kv.next() {
+            Some(x) => x.trim(),
+            None => {
+                let msg = format!("expected `key = value`, found {:?}", part.trim());
+                return Err(Error::new(n, col, ErrorKind::Header(msg)));
+            }
+        };
+
+        let size = |x: &str| {
+            x.parse::<usize>().map_err(|_| {
+                let msg = format!("expected a size for {}, found {:?}", key, x);
+                Error::new(n, col, ErrorKind::Header(msg))
+            })
+        };
+
+        match key {
+            "x" => cols = Some(size(value)?),
+            "y" => rows = Some(size(value)?),
+            "rule" => {
+                // Bounded grid suffixes like `:T20,20` are not supported.
+                let value = value.split(':').next().unwrap_or("");
+                let x = value.parse().map_err(|e| Error::new(n, col, ErrorKind::Rule(e)))?;
+                rule = Some(x);
+            }
+            _ => {
+                let msg = format!("unknown key {:?}", key);
+                return Err(Error::new(n, col, ErrorKind::Header(msg)));
+            }
+        }
+    }
+
+    match (rows, cols) {
+        (Some(rows), Some(cols)) if rows.checked_mul(cols).map_or(true, |x| x > LIMIT) => {
+            Err(Error::new(n, 1, ErrorKind::TooLarge(rows, cols)))
+        }
+        (Some(rows), Some(cols)) => Ok(((rows, cols), rule)),
+        _ => Err(Error::new(n, 1, ErrorKind::Header("missing x or y".into()))),
+    }
+}
+
+
+/// Pattern body reader, keeping track of the position across lines.
+struct Reader {
+    cells: Array2<Cell>,
+    x: usize,
+    y: usize,
+    count: Option<usize>,
+    prefix: Option<usize>,
+    done: bool,
+}
+
+impl Reader {
+    fn new(size: (usize, usize)) -> Self {
+        Reader {
+            cells: Array2::from_elem(size, Cell::Unborn),
+            x: 0,
+            y: 0,
+            count: None,
+            prefix: None,
+            done: false,
+        }
+    }
+
+    fn read(&mut self, line: &str, n: usize) -> Result<(), Error> {
+        for (i, c) in line.chars().enumerate() {
+            let col = i + 1;
+
+            if self.prefix.is_some() && !is_state(c) {
+                return Err(Error::new(n, col, ErrorKind::Unexpected(c)));
+            }
+
+            match c {
+                '!' => {
+                    self.done = true;
+                    return Ok(());
+                }
+                '$' => {
+                    let count = self.take_count();
+                    self.x = self.x.checked_add(count)
+                        .ok_or(Error::new(n, col, ErrorKind::Overflow))?;
+                    self.y = 0;
+                }
+                'b' | '.' => {
+                    let count = self.take_count();
+                    self.place(0, count, n, col)?;
+                }
+                'o' => {
+                    let count = self.take_count();
+                    self.place(1, count, n, col)?;
+                }
+                c if is_state(c) => {
+                    let state = self.prefix.take().unwrap_or(0) + (c as usize - 'A' as usize) + 1;
+                    let count = self.take_count();
+                    self.place(state, count, n, col)?;
+                }
+                c if c >= 'p' && c <= 'y' => {
+                    self.prefix = Some(24 * (c as usize - 'p' as usize + 1));
+                }
+                c if c.is_digit(10) => {
+                    let digit = c.to_digit(10).unwrap() as usize;
+                    let count = self.count.unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|x| x.checked_add(digit))
+                        .ok_or(Error::new(n, col, ErrorKind::Overflow))?;
+                    self.count = Some(count);
+                }
+                c if c.is_whitespace() => {}
+                c => return Err(Error::new(n, col, ErrorKind::Unexpected(c))),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn take_count(&mut self) -> usize {
+        self.count.take().unwrap_or(1)
+    }
+
+    fn place(&mut self, state: usize, count: usize, n: usize, col: usize) -> Result<(), Error> {
+        let (rows, cols) = self.cells.dim();
+        let end = match self.y.checked_add(count) {
+            Some(x) if x <= cols && (state == 0 || self.x < rows) => x,
+            _ => return Err(Error::new(n, col, ErrorKind::Overflow)),
+        };
+
+        if state > 0 {
+            for y in self.y..end {
+                self.cells[[self.x, y]] = match state {
+                    1 => Cell::Alive,
+                    x => Cell::Dead(x - 2),
+                };
+            }
+        }
+
+        self.y = end;
+
+        Ok(())
+    }
+}
+
+fn is_state(c: char) -> bool {
+    c >= 'A' && c <= 'X'
+}
+
+
+fn state(cell: Cell, rule: &Rule) -> usize {
+    match cell {
+        Cell::Alive => 1,
+        Cell::Dead(age) if rule.is_refractory(age) => age + 2,
+        _ => 0,
+    }
+}
+
+fn tag(state: usize, states: usize) -> String {
+    match (states, state) {
+        (2, 0) => "b".into(),
+        (2, _) => "o".into(),
+        (_, 0) => ".".into(),
+        (_, x) => {
+            let prefix = (x - 1) / 24;
+            let letter = ((x - 1) % 24) as u8 + b'A';
+
+            if prefix == 0 {
+                format!("{}", letter as char)
+            } else {
+                format!("{}{}", (b'p' + prefix as u8 - 1) as char, letter as char)
+            }
+        }
+    }
+}
+
+fn token(count: usize, tag: &str) -> String {
+    if count == 1 {
+        tag.to_string()
+    } else {
+        format!("{}{}", count, tag)
+    }
+}
+
+fn encode(cells: &[(Point, usize)], rule: &Rule) -> String {
+    let (min, max) = match bounds(cells.iter().map(|&(x, _)| x)) {
+        Some(x) => x,
+        None => return format!("x = 0, y = 0, rule = {}\n!\n", rule),
+    };
+
+    let rows = (max.0 - min.0 + 1) as usize;
+    let cols = (max.1 - min.1 + 1) as usize;
+    let mut canvas = vec![vec![0; cols]; rows];
+
+    for &(coord, state) in cells {
+        canvas[(coord.0 - min.0) as usize][(coord.1 - min.1) as usize] = state;
+    }
+
+    let mut tokens = vec![];
+    let mut row_ends = 0;
+
+    for (i, row) in canvas.iter().enumerate() {
+        if i > 0 {
+            row_ends += 1;
+        }
+
+        let len = row.iter().rposition(|&x| x > 0).map_or(0, |x| x + 1);
+
+        if len == 0 {
+            continue;
+        }
+
+        if row_ends > 0 {
+            tokens.push(token(row_ends, "$"));
+            row_ends = 0;
+        }
+
+        let mut y = 0;
+        while y < len {
+            let state = row[y];
+            let run = row[y..len].iter().take_while(|&&x| x == state).count();
+            tokens.push(token(run, &tag(state, rule.states())));
+            y += run;
+        }
+    }
+
+    tokens.push("!".into());
+
+    let mut body = String::new();
+    let mut line = 0;
+
+    for token in tokens {
+        if line + token.len() > WIDTH {
+            body.push('\n');
+            line = 0;
+        }
+
+        line += token.len();
+        body.push_str(&token);
+    }
+
+    format!("x = {}, y = {}, rule = {}\n{}\n", cols, rows, rule, body)
+}
+
+#[test]
+fn test_parse() {
+    use pattern::{ Glider, Pattern };
+
+    let s = "#N Glider\n\
+             #O Richard K. Guy\n\
+             #C The smallest, most common, and first discovered spaceship.\n\
+             x = 3, y = 3, rule = B3/S23\n\
+             bob$2bo$\n\
+             3o!";
+    let doc = parse(s).unwrap();
+
+    assert_eq!(doc.name, Some("Glider".into()));
+    assert_eq!(doc.author, Some("Richard K. Guy".into()));
+    assert_eq!(doc.comments.len(), 1);
+    assert_eq!(doc.rule, Some(Rule::conway()));
+    assert_eq!(doc.pattern.0, Glider::BottomRight.pattern());
+}
+
+#[test]
+fn test_parse_multistate() {
+    use grid::GridItem;
+
+    let doc = parse("x = 4, y = 3, rule = B2/S/C3\n.A2B$\n\n3.pA!").unwrap();
+    let cells = doc.pattern.0;
+
+    assert_eq!(cells.dim(), (3, 4));
+    assert_eq!(cells[[0, 1]], Cell::Alive);
+    assert_eq!(cells[[0, 2]].age(), Some(0));
+    assert_eq!(cells[[0, 3]].age(), Some(0));
+    assert_eq!(cells[[1, 3]].age(), Some(23));
+    assert_eq!(cells[[2, 3]], Cell::Unborn);
+}
+
+#[test]
+fn test_parse_errors() {
+    let xs = vec![
+        ("bo$2bo$3o!", 1, 1),
+        ("x = 3, y = 3\nbob$2bk!", 2, 7),
+        ("x = 2, y = 1\nbo$\n3o!", 3, 2),
+        ("x = 3, y = 3\n4o!", 2, 2),
+        ("x = 3, z = 3", 1, 8),
+        ("x = 3, y = 3, rule = life\n3o!", 1, 15),
+        ("#C nothing else", 2, 1),
+        ("x = 3, y = 3\n99999999999999999999999o!", 2, 20),
+        ("x = 3, y = 3\no18446744073709551615b!", 2, 22),
+        ("x = 3, y = 3\n18446744073709551615$$!", 2, 22),
+    ];
+
+    for (s, line, column) in xs {
+        let err = parse(s).unwrap_err();
+        assert_eq!((err.line, err.column), (line, column), "{:?}: {}", s, err);
+    }
+}
+
+#[test]
+fn test_parse_too_large() {
+    let xs = vec![
+        ("x = 4000000000, y = 4000000000
+!", 1, (4000000000, 4000000000)),
+        ("#N Huge
+x = 100000, y = 100000
+!", 2, (100000, 100000)),
+    ];
+
+    for (s, line, size) in xs {
+        let err = parse(s).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TooLarge(size.0, size.1), "{:?}", s);
+        assert_eq!((err.line, err.column), (line, 1), "{:?}", s);
+    }
+}
+
+#[test]
+fn test_write() {
+    use community::Community;
+    use pattern::{ Glider, Layout };
+    use population::{ Population, glider };
+
+    let expected = "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+
+    let mut grid = Community::empty(10);
+    grid.insert(Layout::new((2, 3), Glider::BottomRight));
+
+    assert_eq!(write(grid), expected);
+    assert_eq!(write(glider(Population::empty(10), (4, 4))), expected);
+}
+
+#[test]
+fn test_write_roundtrip() {
+    use colony::Colony;
+    use pattern::Layout;
+
+    let s = "x = 12, y = 5, rule = B2/S/C3\n2A8.B$3.B3$12A!\n";
+    let doc = parse(s).unwrap();
+
+    let mut grid = Colony::empty().with_rule(doc.rule.unwrap());
+    grid.insert(Layout::new((0, 0), doc.pattern));
+
+    assert_eq!(write(grid), s);
+}
+
+#[test]
+fn test_write_wraps() {
+    use population::Population;
+
+    let mut grid = Population::empty(80);
+    for y in 0..80 {
+        if y % 2 == 0 {
+            grid.regenerate((0, y));
+        }
+    }
+
+    let s = write(grid);
+
+    assert!(s.lines().all(|x| x.len() <= WIDTH));
+    assert_eq!(parse(&s).unwrap().pattern.0.dim(), (1, 79));
+}
@@ -1,7 +1,7 @@
 //! Grid traits. Abstraction over a Conway grid, a collection of items with a
 //! set of rules to decide their next state (fate).
 //!
-//! Conway rules:
+//! Conway rules (the default, see `rule::Rule` for others):
 //!
 //! 1. Any live cell with fewer than two live neighbours dies, as if caused
 //! by underpopulation.
@@ -14,38 +14,46 @@
 
 use std::fmt::Display;
 
+use rule::{ self, Rule };
+
 
 pub trait Grid: Clone + Display + IntoIterator {
     type Cell: GridItem;
     type Coord: GridCoord + Clone;
 
 
-    /// The size of the grid. E.g. A 3 x 3 grid has size 3.
-    fn size(&self) -> usize;
+    /// The size of the grid as rows and columns. E.g. A 3 x 5 grid has size
+    /// `(3, 5)`.
+    fn size(&self) -> (usize, usize);
 
     /// The value of the item positioned at the given coords.
     fn item(&self, coord: Self::Coord) -> Self::Cell;
 
-    /// The item neighbours.
+    /// The item neighbours. Isotropic rules expect the 8 of them in reading
+    /// order.
     fn item_neighbours(&self, coord: Self::Coord) -> Vec<Self::Cell>;
 
     fn evolve(&self) -> Self;
 
+    /// The rule used to decide the fate of each item.
+    fn rule(&self) -> Rule {
+        Rule::default()
+    }
+
     /// Evolves the item to its next state.
     fn item_fate(&self, coord: Self::Coord) -> Self::Cell {
-        let count = self.item_neighbours(coord.clone())
-                        .into_iter()
+        let rule = self.rule();
+        let neighbours = self.item_neighbours(coord.clone());
+
+        if !rule.is_totalistic() {
+            return rule.configuration_fate(self.item(coord), rule::configuration(&neighbours));
+        }
+
+        let count = neighbours.into_iter()
                         .filter(|x| x.is_alive())
                         .count();
-        let cell = self.item(coord);
-        let is_alive = (&cell).is_alive();
-
-        match (is_alive,  count) {
-            (true, 2...3) => cell.keep(),
-            (true, _)     => cell.kill(),
-            (false, 3)    => cell.revive(),
-            (false, _)    => cell.rot(),
-        }
+
+        rule.fate(self.item(coord), count)
     }
 }
 
@@ -53,6 +61,9 @@ pub trait Grid: Clone + Display + IntoIterator {
 pub trait GridItem {
     fn is_alive(&self) -> bool;
 
+    /// Generations since the item died, if dead.
+    fn age(&self) -> Option<usize>;
+
     /// Keep as is.
     fn keep(&self) -> Self;
 
@@ -0,0 +1,1039 @@
+//! Grid implementation using HashLife.
+//!
+//! The universe is a quadtree of hash consed nodes: identical squares are
+//! stored once, no matter where or when they appear. The future of each node
+//! is memoized, which lets the grid jump `2^k` generations at once.
+//!
+//! Clones and successors of a grid share its nodes and memoized futures, so
+//! evolving through `Grid::evolve` costs no more than `step`.
+//!
+//! Only two state rules without birth on 0 neighbours (B0) are supported,
+//! isotropic ones included.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use ndarray::prelude::*;
+
+use coord::Point as Coord;
+use cell::Cell;
+use format::macrocell::{ self, Macrocell };
+use grid::Grid;
+use pattern::{ Pattern, Layout };
+use rule::Rule;
+
+
+type Id = usize;
+
+const DEAD: Id = 0;
+const ALIVE: Id = 1;
+
+/// Node count above which unreachable nodes are collected after a step.
+const NODE_LIMIT: usize = 1 << 22;
+
+/// The largest `k` of `step_pow2`. The root grows to at least `k + 3` levels
+/// and coordinates of its corners must fit in an `isize`, with room left for
+/// the pattern itself.
+pub const MAX_STEP: u8 = 56;
+
+
+#[derive(Debug, Copy, Clone)]
+struct Node {
+    level: u8,
+    nw: Id,
+    ne: Id,
+    sw: Id,
+    se: Id,
+    population: usize,
+}
+
+
+/// Node cache statistics.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Nodes currently stored.
+    pub nodes: usize,
+    /// Memoized node futures currently stored.
+    pub results: usize,
+    /// Futures found in the cache.
+    pub hits: usize,
+    /// Futures computed.
+    pub misses: usize,
+    /// Garbage collections run.
+    pub collections: usize,
+}
+
+
+/// Nodes and futures shared by every grid evolved from the same one. The
+/// root of each grid is kept here, by slot, so that collecting garbage
+/// leaves all of them alive.
+#[derive(Debug, Clone)]
+struct Store {
+    nodes: Vec<Node>,
+    index: HashMap<(Id, Id, Id, Id), Id>,
+    results: HashMap<(Id, u8), Id>,
+    empties: Vec<Id>,
+    roots: HashMap<usize, Id>,
+    slots: usize,
+    rule: Rule,
+    stats: Stats,
+}
+
+
+#[derive(Debug)]
+pub struct HashLife {
+    store: Rc<RefCell<Store>>,
+    slot: usize,
+    gen: usize,
+    limit: usize,
+}
+
+impl HashLife {
+    pub fn empty() -> Self {
+        let leaf = |population| Node {
+            level: 0,
+            nw: DEAD,
+            ne: DEAD,
+            sw: DEAD,
+            se: DEAD,
+            population: population,
+        };
+
+        let mut store = Store {
+            nodes: vec![leaf(0), leaf(1)],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empties: vec![DEAD],
+            roots: HashMap::new(),
+            slots: 0,
+            rule: Rule::default(),
+            stats: Stats::default(),
+        };
+        let root = store.empty_node(3);
+        let slot = store.add(root);
+
+        HashLife {
+            store: Rc::new(RefCell::new(store)),
+            slot: slot,
+            gen: 1,
+            limit: NODE_LIMIT,
+        }
+    }
+
+    /// Loads a macrocell quadtree as is, centred on the origin. Its
+    /// generation is taken as the one before the first.
+    ///
+    /// Fails if the tree has a rule HashLife doesn't support.
+    pub fn from_macrocell(tree: &Macrocell) -> Result<Self, String> {
+        let mut grid = HashLife::empty();
+        let mut ids: Vec<Id> = vec![];
+
+        if let Some(rule) = tree.rule {
+            grid = grid.try_with_rule(rule)?;
+        }
+
+        {
+            let mut store = grid.store.borrow_mut();
+
+            for node in &tree.nodes {
+                let id = match *node {
+                    macrocell::Node::Leaf(rows) => store.leaf(rows, 0, 0, 3),
+                    macrocell::Node::Branch { level, nw, ne, sw, se } => {
+                        let e = store.empty_node(level - 1);
+                        let child = |x: usize| if x == 0 { e } else { ids[x - 1] };
+
+                        store.join(child(nw), child(ne), child(sw), child(se))
+                    }
+                };
+
+                ids.push(id);
+            }
+
+            if let Some(&root) = ids.last() {
+                store.roots.insert(grid.slot, root);
+            }
+        }
+
+        grid.gen = tree.generation.map_or(1, |x| x + 1);
+
+        Ok(grid)
+    }
+
+    /// Whether the rule is a two state one without birth on 0 neighbours.
+    pub fn supports(rule: Rule) -> bool {
+        rule.states() <= 2 && !rule.is_born(0)
+    }
+
+    /// Evolves with the given rule instead of Conway's.
+    ///
+    /// Panics if the rule is a Generations rule or has birth on 0 neighbours,
+    /// see `try_with_rule`.
+    pub fn with_rule(self, rule: Rule) -> Self {
+        self.try_with_rule(rule).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Evolves with the given rule instead of Conway's, failing if HashLife
+    /// doesn't support it.
+    pub fn try_with_rule(mut self, rule: Rule) -> Result<Self, String> {
+        if !HashLife::supports(rule) {
+            return Err(format!("HashLife does not support the rule {}", rule));
+        }
+
+        // Futures depend on the rule, the grid leaves the store of its clones.
+        let root = self.root();
+        let mut store = self.store.borrow().clone();
+        store.results.clear();
+        store.roots.clear();
+        store.rule = rule;
+
+        self.store.borrow_mut().roots.remove(&self.slot);
+        self.slot = store.add(root);
+        self.store = Rc::new(RefCell::new(store));
+        Ok(self)
+    }
+
+    /// Collects garbage whenever the cache grows beyond `limit` nodes.
+    pub fn with_node_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn regenerate<T: Into<Coord>>(&mut self, coord: T) {
+        self.set(coord.into(), true);
+    }
+
+    /// Mix in a pattern
+    pub fn insert<T: Pattern<Array2<Cell>>>(&mut self, layout: Layout<T>) {
+        let (x, y) = layout.offset();
+        let pattern = layout.pattern();
+
+        for ((i, j), cell) in pattern.indexed_iter() {
+            let coord = Coord((x + i) as isize, (y + j) as isize);
+            self.set(coord, *cell == Cell::Alive);
+        }
+    }
+
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+
+    /// Number of alive cells.
+    pub fn population(&self) -> usize {
+        self.store.borrow().nodes[self.root()].population
+    }
+
+    /// Statistics of the cache shared with clones and successors.
+    pub fn stats(&self) -> Stats {
+        let store = self.store.borrow();
+
+        Stats {
+            nodes: store.nodes.len(),
+            results: store.results.len(),
+            .. store.stats
+        }
+    }
+
+    /// The smallest rectangle holding every alive cell, as its top left and
+    /// bottom right corners (inclusive).
+    pub fn bounds(&self) -> Option<(Coord, Coord)> {
+        let cells = self.alive();
+        let first = match cells.first() {
+            Some(x) => *x,
+            None => return None,
+        };
+
+        Some(cells.iter().fold((first, first), |(min, max), c| {
+            (Coord(min.0.min(c.0), min.1.min(c.1)),
+             Coord(max.0.max(c.0), max.1.max(c.1)))
+        }))
+    }
+
+    /// Every alive cell.
+    pub fn alive(&self) -> Vec<Coord> {
+        let mut cells = vec![];
+        let origin = self.origin();
+        self.store.borrow().collect(self.root(), origin, origin, &mut cells);
+
+        cells
+    }
+
+    /// Advances one generation.
+    pub fn step(&mut self) {
+        self.step_pow2(0);
+    }
+
+    /// Advances `n` generations.
+    ///
+    /// Panics if `n` is `2^(MAX_STEP + 1)` or more, see `step_pow2`.
+    pub fn step_by(&mut self, n: usize) {
+        for k in 0..(0usize.count_zeros() as u8) {
+            if n & (1 << k) != 0 {
+                self.step_pow2(k);
+            }
+        }
+    }
+
+    /// Advances `2^k` generations at once.
+    ///
+    /// Panics if `k` is above `MAX_STEP` or the generation overflows.
+    pub fn step_pow2(&mut self, k: u8) {
+        if k > MAX_STEP {
+            panic!("Cannot step 2^{} generations at once, at most 2^{}", k, MAX_STEP);
+        }
+
+        let gen = self.gen.checked_add(1 << k).expect("Generation overflow");
+        let mut store = self.store.borrow_mut();
+        let mut root = store.roots[&self.slot];
+
+        while store.nodes[root].level < k + 3 || !store.is_padded(root) {
+            root = store.expand(root);
+        }
+
+        root = store.successor(root, k);
+        store.roots.insert(self.slot, root);
+        self.gen = gen;
+
+        if store.nodes.len() > self.limit {
+            store.gc();
+        }
+    }
+
+    /// Drops every node not reachable from this grid or the others sharing
+    /// its store, together with the futures memoized for them.
+    pub fn gc(&mut self) {
+        self.store.borrow_mut().gc();
+    }
+
+
+    /// Writes the universe as a macrocell quadtree, without expanding it.
+    pub fn to_macrocell(&self) -> Macrocell {
+        let mut store = self.store.borrow_mut();
+        let mut tree = Macrocell::new();
+        tree.rule = Some(store.rule);
+        tree.generation = Some(self.gen - 1);
+
+        // Macrocell leaves are 8 x 8, the root must be a branch above them.
+        let mut root = store.roots[&self.slot];
+
+        while store.nodes[root].level < 4 {
+            root = store.expand(root);
+        }
+
+        store.export(root, &mut tree, &mut HashMap::new());
+
+        tree
+    }
+
+
+    fn root(&self) -> Id {
+        self.store.borrow().roots[&self.slot]
+    }
+
+    /// Coordinate of the top left corner of the root node.
+    fn origin(&self) -> isize {
+        let store = self.store.borrow();
+        store.origin(store.roots[&self.slot])
+    }
+
+    fn set(&mut self, coord: Coord, alive: bool) {
+        let mut store = self.store.borrow_mut();
+        let mut root = store.roots[&self.slot];
+
+        loop {
+            let origin = store.origin(root);
+            let end = -origin;
+
+            if coord.0 >= origin && coord.0 < end && coord.1 >= origin && coord.1 < end {
+                break;
+            }
+
+            root = store.expand(root);
+        }
+
+        let origin = store.origin(root);
+        let (x, y) = ((coord.0 - origin) as usize, (coord.1 - origin) as usize);
+
+        root = store.set_node(root, x, y, alive);
+        store.roots.insert(self.slot, root);
+    }
+}
+
+
+impl Clone for HashLife {
+    /// Shares the store, only the root is copied.
+    fn clone(&self) -> Self {
+        let root = self.root();
+
+        HashLife {
+            store: self.store.clone(),
+            slot: self.store.borrow_mut().add(root),
+            gen: self.gen,
+            limit: self.limit,
+        }
+    }
+}
+
+
+impl Drop for HashLife {
+    fn drop(&mut self) {
+        self.store.borrow_mut().roots.remove(&self.slot);
+    }
+}
+
+
+impl Store {
+    /// Keeps the given root alive, returning its slot.
+    fn add(&mut self, root: Id) -> usize {
+        self.slots += 1;
+        self.roots.insert(self.slots, root);
+
+        self.slots
+    }
+
+    fn gc(&mut self) {
+        let mut remap: HashMap<Id, Id> = HashMap::new();
+        let mut nodes = vec![self.nodes[DEAD], self.nodes[ALIVE]];
+        remap.insert(DEAD, DEAD);
+        remap.insert(ALIVE, ALIVE);
+
+        let roots = self.roots.iter()
+            .map(|(&slot, &root)| (slot, self.mark(root, &mut nodes, &mut remap)))
+            .collect();
+
+        let index = nodes.iter()
+            .enumerate()
+            .filter(|&(_, node)| node.level > 0)
+            .map(|(id, node)| ((node.nw, node.ne, node.sw, node.se), id))
+            .collect();
+
+        let results = self.results.iter()
+            .filter_map(|(&(id, k), result)| {
+                match (remap.get(&id), remap.get(result)) {
+                    (Some(&id), Some(&result)) => Some(((id, k), result)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        self.nodes = nodes;
+        self.index = index;
+        self.results = results;
+        self.empties = vec![DEAD];
+        self.roots = roots;
+        self.stats.collections += 1;
+    }
+
+    fn mark(&self, id: Id, nodes: &mut Vec<Node>, remap: &mut HashMap<Id, Id>) -> Id {
+        if let Some(&x) = remap.get(&id) {
+            return x;
+        }
+
+        let node = self.nodes[id];
+        let new = Node {
+            nw: self.mark(node.nw, nodes, remap),
+            ne: self.mark(node.ne, nodes, remap),
+            sw: self.mark(node.sw, nodes, remap),
+            se: self.mark(node.se, nodes, remap),
+            .. node
+        };
+
+        nodes.push(new);
+        remap.insert(id, nodes.len() - 1);
+
+        nodes.len() - 1
+    }
+
+    fn export(&self, id: Id, tree: &mut Macrocell, remap: &mut HashMap<Id, usize>) -> usize {
+        if let Some(&x) = remap.get(&id) {
+            return x;
+        }
+
+        let node = self.nodes[id];
+
+        if node.population == 0 {
+            return 0;
+        }
+
+        let res = if node.level <= 3 {
+            let mut rows = [0; 8];
+
+            for (x, y) in iproduct!(0..1 << node.level, 0..1 << node.level) {
+                if self.get(id, x, y) {
+                    rows[x] |= 1 << y;
+                }
+            }
+
+            macrocell::Node::Leaf(rows)
+        } else {
+            macrocell::Node::Branch {
+                level: node.level,
+                nw: self.export(node.nw, tree, remap),
+                ne: self.export(node.ne, tree, remap),
+                sw: self.export(node.sw, tree, remap),
+                se: self.export(node.se, tree, remap),
+            }
+        };
+
+        tree.nodes.push(res);
+        remap.insert(id, tree.nodes.len());
+
+        tree.nodes.len()
+    }
+
+    /// Builds the node of `2^level` cells at `(x, y)` of an 8 x 8 leaf.
+    fn leaf(&mut self, rows: [u8; 8], x: usize, y: usize, level: u8) -> Id {
+        if level == 0 {
+            return if rows[x] & (1 << y) != 0 { ALIVE } else { DEAD };
+        }
+
+        let half = 1 << (level - 1);
+        let nw = self.leaf(rows, x, y, level - 1);
+        let ne = self.leaf(rows, x, y + half, level - 1);
+        let sw = self.leaf(rows, x + half, y, level - 1);
+        let se = self.leaf(rows, x + half, y + half, level - 1);
+
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Coordinate of the top left corner of the given root node.
+    fn origin(&self, root: Id) -> isize {
+        -(1 << (self.nodes[root].level - 1))
+    }
+
+    fn join(&mut self, nw: Id, ne: Id, sw: Id, se: Id) -> Id {
+        if let Some(&id) = self.index.get(&(nw, ne, sw, se)) {
+            return id;
+        }
+
+        let node = Node {
+            level: self.nodes[nw].level + 1,
+            nw: nw,
+            ne: ne,
+            sw: sw,
+            se: se,
+            population: self.nodes[nw].population + self.nodes[ne].population +
+                        self.nodes[sw].population + self.nodes[se].population,
+        };
+
+        self.nodes.push(node);
+        self.index.insert((nw, ne, sw, se), self.nodes.len() - 1);
+
+        self.nodes.len() - 1
+    }
+
+    fn empty_node(&mut self, level: u8) -> Id {
+        while self.empties.len() <= level as usize {
+            let e = *self.empties.last().unwrap();
+            let node = self.join(e, e, e, e);
+            self.empties.push(node);
+        }
+
+        self.empties[level as usize]
+    }
+
+    /// The centred node one level down.
+    fn centre(&mut self, id: Id) -> Id {
+        let node = self.nodes[id];
+        let (nw, ne, sw, se) = (self.nodes[node.nw], self.nodes[node.ne],
+                                self.nodes[node.sw], self.nodes[node.se]);
+
+        self.join(nw.se, ne.sw, sw.ne, se.nw)
+    }
+
+    /// Whether every alive cell sits in the centre half of the root.
+    fn is_padded(&mut self, root: Id) -> bool {
+        let centre = self.centre(root);
+        let inner = self.centre(centre);
+
+        self.nodes[inner].population == self.nodes[root].population
+    }
+
+    /// Doubles the root size keeping its contents centred.
+    fn expand(&mut self, root: Id) -> Id {
+        let root = self.nodes[root];
+        let e = self.empty_node(root.level - 1);
+
+        let nw = self.join(e, e, e, root.nw);
+        let ne = self.join(e, e, root.ne, e);
+        let sw = self.join(e, root.sw, e, e);
+        let se = self.join(root.se, e, e, e);
+
+        self.join(nw, ne, sw, se)
+    }
+
+    /// The centred node one level down, `2^k` generations ahead. Requires
+    /// `k <= level - 2`.
+    fn successor(&mut self, id: Id, k: u8) -> Id {
+        let node = self.nodes[id];
+
+        if node.population == 0 {
+            return self.empty_node(node.level - 1);
+        }
+
+        if let Some(&x) = self.results.get(&(id, k)) {
+            self.stats.hits += 1;
+            return x;
+        }
+
+        self.stats.misses += 1;
+
+        let result = if node.level == 2 {
+            self.base(id)
+        } else {
+            let (nw, ne, sw, se) = (self.nodes[node.nw], self.nodes[node.ne],
+                                    self.nodes[node.sw], self.nodes[node.se]);
+
+            let n00 = node.nw;
+            let n01 = self.join(nw.ne, ne.nw, nw.se, ne.sw);
+            let n02 = node.ne;
+            let n10 = self.join(nw.sw, nw.se, sw.nw, sw.ne);
+            let n11 = self.join(nw.se, ne.sw, sw.ne, se.nw);
+            let n12 = self.join(ne.sw, ne.se, se.nw, se.ne);
+            let n20 = node.sw;
+            let n21 = self.join(sw.ne, se.nw, sw.se, se.sw);
+            let n22 = node.se;
+
+            // At full speed both halves advance in time, otherwise only the
+            // second one does.
+            let full = k + 2 == node.level;
+            let next = if full { k - 1 } else { k };
+
+            let mut xs = [n00, n01, n02, n10, n11, n12, n20, n21, n22];
+            for x in xs.iter_mut() {
+                *x = if full { self.successor(*x, next) } else { self.centre(*x) };
+            }
+
+            let a = self.join(xs[0], xs[1], xs[3], xs[4]);
+            let b = self.join(xs[1], xs[2], xs[4], xs[5]);
+            let c = self.join(xs[3], xs[4], xs[6], xs[7]);
+            let d = self.join(xs[4], xs[5], xs[7], xs[8]);
+
+            let a = self.successor(a, next);
+            let b = self.successor(b, next);
+            let c = self.successor(c, next);
+            let d = self.successor(d, next);
+
+            self.join(a, b, c, d)
+        };
+
+        self.results.insert((id, k), result);
+
+        result
+    }
+
+    /// The centred 2 x 2 node of a 4 x 4 node, one generation ahead.
+    fn base(&mut self, id: Id) -> Id {
+        let mut bits = [[false; 4]; 4];
+
+        for (x, y) in iproduct!(0..4, 0..4) {
+            bits[x][y] = self.get(id, x, y);
+        }
+
+        let fate = |x: usize, y: usize| {
+            let configuration = iproduct!(x - 1..x + 2, y - 1..y + 2)
+                .filter(|&(i, j)| (i, j) != (x, y))
+                .enumerate()
+                .filter(|&(_, (i, j))| bits[i][j])
+                .fold(0, |acc, (k, _)| acc | 1 << k);
+
+            let alive = if bits[x][y] {
+                self.rule.survives_in(configuration)
+            } else {
+                self.rule.is_born_in(configuration)
+            };
+
+            if alive { ALIVE } else { DEAD }
+        };
+
+        let (nw, ne, sw, se) = (fate(1, 1), fate(1, 2), fate(2, 1), fate(2, 2));
+
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Whether the cell at `(x, y)`, relative to the node top left corner, is
+    /// alive.
+    fn get(&self, id: Id, x: usize, y: usize) -> bool {
+        let node = self.nodes[id];
+
+        if node.level == 0 {
+            return id == ALIVE;
+        }
+
+        if node.population == 0 {
+            return false;
+        }
+
+        let half = 1 << (node.level - 1);
+        let child = match (x < half, y < half) {
+            (true, true) => node.nw,
+            (true, false) => node.ne,
+            (false, true) => node.sw,
+            (false, false) => node.se,
+        };
+
+        self.get(child, x % half, y % half)
+    }
+
+    fn set_node(&mut self, id: Id, x: usize, y: usize, alive: bool) -> Id {
+        let node = self.nodes[id];
+
+        if node.level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+
+        let half = 1 << (node.level - 1);
+        let (mut nw, mut ne, mut sw, mut se) = (node.nw, node.ne, node.sw, node.se);
+
+        match (x < half, y < half) {
+            (true, true) => nw = self.set_node(nw, x % half, y % half, alive),
+            (true, false) => ne = self.set_node(ne, x % half, y % half, alive),
+            (false, true) => sw = self.set_node(sw, x % half, y % half, alive),
+            (false, false) => se = self.set_node(se, x % half, y % half, alive),
+        }
+
+        self.join(nw, ne, sw, se)
+    }
+
+    fn collect(&self, id: Id, x: isize, y: isize, cells: &mut Vec<Coord>) {
+        let node = self.nodes[id];
+
+        if node.population == 0 {
+            return;
+        }
+
+        if node.level == 0 {
+            cells.push(Coord(x, y));
+            return;
+        }
+
+        let half = 1 << (node.level - 1);
+
+        self.collect(node.nw, x, y, cells);
+        self.collect(node.ne, x, y + half, cells);
+        self.collect(node.sw, x + half, y, cells);
+        self.collect(node.se, x + half, y + half, cells);
+    }
+}
+
+
+impl Grid for HashLife {
+    type Cell = Cell;
+    type Coord = Coord;
+
+    /// The size of the bounding box.
+    fn size(&self) -> (usize, usize) {
+        match self.bounds() {
+            Some((min, max)) => ((max.0 - min.0 + 1) as usize, (max.1 - min.1 + 1) as usize),
+            None => (0, 0),
+        }
+    }
+
+
+    fn rule(&self) -> Rule {
+        self.store.borrow().rule
+    }
+
+
+    fn item(&self, coord: Coord) -> Cell {
+        let origin = self.origin();
+        let end = -origin;
+
+        if coord.0 < origin || coord.0 >= end || coord.1 < origin || coord.1 >= end {
+            return Cell::Unborn;
+        }
+
+        let (x, y) = ((coord.0 - origin) as usize, (coord.1 - origin) as usize);
+
+        if self.store.borrow().get(self.root(), x, y) { Cell::Alive } else { Cell::Unborn }
+    }
+
+
+    fn item_neighbours(&self, coord: Coord) -> Vec<Cell> {
+        iproduct!(-1..2, -1..2)
+            .filter(|&d| d != (0, 0))
+            .map(|(dx, dy)| self.item(coord.offset(dx, dy)))
+            .collect()
+    }
+
+    fn evolve(&self) -> Self {
+        let mut next = self.clone();
+        next.step();
+
+        next
+    }
+}
+
+
+impl IntoIterator for HashLife {
+    type Item = (Coord, Cell);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    /// Every cell inside the bounding box.
+    fn into_iter(self) -> Self::IntoIter {
+        let (min, max) = match self.bounds() {
+            Some(x) => x,
+            None => return vec![].into_iter(),
+        };
+
+        iproduct!(min.0..max.0 + 1, min.1..max.1 + 1)
+            .map(|(x, y)| (Coord(x, y), self.item(Coord(x, y))))
+            .collect::<Vec<(Coord, Cell)>>()
+            .into_iter()
+    }
+}
+
+
+impl fmt::Display for HashLife {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (min, max) = match self.bounds() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        for x in min.0..max.0 + 1 {
+            let row = (min.1..max.1 + 1)
+                .map(|y| format!("{}", self.item(Coord(x, y))))
+                .collect::<String>();
+
+            writeln!(f, "{}", row)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+fn r_pentomino() -> Vec<(isize, isize)> {
+    vec![(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)]
+}
+
+#[test]
+fn test_step_pow2() {
+    use colony::Colony;
+
+    let mut hashlife = HashLife::empty();
+    let mut colony = Colony::empty();
+
+    for coord in r_pentomino() {
+        hashlife.regenerate(coord);
+        colony.regenerate(coord);
+    }
+
+    hashlife.step_pow2(7);
+
+    for _ in 0..128 {
+        colony = colony.evolve();
+    }
+
+    let mut expected: Vec<Coord> = colony.into_iter()
+        .filter(|&(_, x)| x == Cell::Alive)
+        .map(|(x, _)| x)
+        .collect();
+    let mut actual = hashlife.alive();
+
+    expected.sort();
+    actual.sort();
+
+    assert_eq!(actual, expected);
+    assert_eq!(hashlife.generation(), 129);
+}
+
+#[test]
+fn test_step_by() {
+    let mut a = HashLife::empty();
+    let mut b = HashLife::empty();
+
+    for coord in r_pentomino() {
+        a.regenerate(coord);
+        b.regenerate(coord);
+    }
+
+    a.step_by(100);
+
+    for _ in 0..100 {
+        b = b.evolve();
+    }
+
+    assert_eq!(a.generation(), b.generation());
+    assert_eq!(format!("{}", a), format!("{}", b));
+}
+
+#[test]
+fn test_glider_pow2() {
+    use pattern::Glider;
+
+    let mut hashlife = HashLife::empty();
+    hashlife.insert(Layout::new((0, 0), Glider::BottomRight));
+
+    let shape = format!("{}", hashlife);
+    hashlife.step_pow2(20);
+
+    let d = 1 << 18;
+    assert_eq!(hashlife.population(), 5);
+    assert_eq!(hashlife.bounds(), Some((Coord(d, d), Coord(d + 2, d + 2))));
+    assert_eq!(format!("{}", hashlife), shape);
+}
+
+#[test]
+fn test_gc() {
+    let mut hashlife = HashLife::empty();
+    let mut expected = HashLife::empty();
+
+    for coord in r_pentomino() {
+        hashlife.regenerate(coord);
+        expected.regenerate(coord);
+    }
+
+    for _ in 0..50 {
+        hashlife.step_pow2(3);
+    }
+
+    expected.step_by(400);
+
+    let stats = hashlife.stats();
+    assert_eq!(stats.collections, 0);
+    assert!(stats.hits > 0);
+
+    hashlife.gc();
+
+    assert_eq!(hashlife.stats().collections, 1);
+    assert!(hashlife.stats().nodes < stats.nodes);
+    assert_eq!(format!("{}", hashlife), format!("{}", expected));
+
+    hashlife.step_pow2(3);
+    expected.step_pow2(3);
+    assert_eq!(format!("{}", hashlife), format!("{}", expected));
+}
+
+#[test]
+fn test_node_limit() {
+    let mut hashlife = HashLife::empty().with_node_limit(1000);
+
+    for coord in r_pentomino() {
+        hashlife.regenerate(coord);
+    }
+
+    for _ in 0..50 {
+        hashlife.step_pow2(3);
+        assert!(hashlife.stats().nodes <= 1000);
+    }
+
+    assert!(hashlife.stats().collections > 0);
+    assert_eq!(hashlife.generation(), 401);
+}
+
+#[test]
+fn test_shared_store() {
+    let mut hashlife = HashLife::empty();
+
+    for coord in r_pentomino() {
+        hashlife.regenerate(coord);
+    }
+
+    let first = format!("{}", hashlife);
+    let mut next = hashlife.evolve();
+
+    for _ in 0..100 {
+        next = next.evolve();
+    }
+
+    assert_eq!(next.stats(), hashlife.stats());
+    assert!(next.stats().hits > 0);
+
+    // Collecting keeps every grid sharing the store.
+    next.gc();
+    next.step_pow2(3);
+
+    let mut expected = HashLife::empty();
+    for coord in r_pentomino() {
+        expected.regenerate(coord);
+    }
+    expected.step_by(109);
+
+    assert_eq!(format!("{}", hashlife), first);
+    assert_eq!(format!("{}", next), format!("{}", expected));
+    assert_eq!(next.generation(), 110);
+}
+
+#[test]
+fn test_macrocell() {
+    let mut hashlife = HashLife::empty();
+
+    for coord in r_pentomino() {
+        hashlife.regenerate(coord);
+    }
+
+    hashlife.step_by(1000);
+
+    let s = hashlife.to_macrocell().to_string();
+    let mut copy = HashLife::from_macrocell(&macrocell::read(&s).unwrap()).unwrap();
+
+    assert!(s.contains("#G 1000\n"));
+    assert_eq!(copy.generation(), hashlife.generation());
+    assert_eq!(format!("{}", copy), format!("{}", hashlife));
+
+    hashlife.step_pow2(6);
+    copy.step_pow2(6);
+    assert_eq!(format!("{}", copy), format!("{}", hashlife));
+}
+
+#[test]
+fn test_macrocell_small() {
+    let mut hashlife = HashLife::empty();
+
+    for &(x, y) in &[(-1, -1), (-1, 0), (0, -1), (0, 0)] {
+        hashlife.regenerate(Coord(x, y));
+    }
+
+    // A block fits in the smallest root, one generation leaves 4 x 4 cells.
+    hashlife.step_pow2(0);
+
+    let tree = macrocell::read(&hashlife.to_macrocell().to_string()).unwrap();
+    let copy = HashLife::from_macrocell(&tree).unwrap();
+
+    assert!(tree.level() >= 4);
+    assert_eq!(copy.alive(), hashlife.alive());
+}
+
+#[test]
+#[should_panic(expected = "at most")]
+fn test_step_too_far() {
+    HashLife::empty().step_pow2(MAX_STEP + 1);
+}
+
+#[test]
+fn test_macrocell_huge() {
+    use format::LIMIT;
+
+    let mut s = String::from("[M2]\n*$\n4 1 0 0 1\n");
+
+    for level in 5..41 {
+        s.push_str(&format!("{} {} 0 0 {}\n", level, level - 3, level - 3));
+    }
+
+    let tree = macrocell::read(&s).unwrap();
+    let mut hashlife = HashLife::from_macrocell(&tree).unwrap();
+
+    assert!(tree.expand(LIMIT).is_err());
+    assert_eq!(hashlife.population(), 1 << 37);
+
+    hashlife.step();
+    assert_eq!(hashlife.population(), 0);
+    assert_eq!(hashlife.to_macrocell().nodes.len(), 0);
+}
+
+#[test]
+fn test_macrocell_rule() {
+    for s in vec!["[M2]\n#R B3/S23/C3\n.*$\n", "[M2]\n#R B03/S23\n.*$\n"] {
+        let tree = macrocell::read(s).unwrap();
+        let err = HashLife::from_macrocell(&tree).unwrap_err();
+
+        assert!(err.contains("does not support"), "{:?}: {}", s, err);
+    }
+
+    let tree = macrocell::read("[M2]\n#R B36/S23\n.*$\n").unwrap();
+    assert_eq!(HashLife::from_macrocell(&tree).unwrap().rule(), "B36/S23".parse().unwrap());
+    assert!(HashLife::empty().try_with_rule("B03/S23".parse().unwrap()).is_err());
+}
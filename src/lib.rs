@@ -5,14 +5,33 @@ extern crate sdl2;
 extern crate rand;
 #[macro_use] extern crate itertools;
 #[macro_use] extern crate ndarray;
+extern crate num_cpus;
 
 
 pub mod grid;
+pub mod rule;
+pub mod neighbourhood;
+pub mod ltl;
+pub mod table;
+pub mod topology;
 pub mod pattern;
 pub mod coord;
 pub mod cell;
 pub mod world;
 pub mod population;
 pub mod community;
+pub mod colony;
+pub mod hashlife;
+pub mod automaton;
+pub mod turmite;
+pub mod spacetime;
+pub mod volume;
+pub mod tiling;
+pub mod bitboard;
+pub mod format;
+pub mod analysis;
+pub mod census;
+pub mod apgcode;
+pub mod soup;
 
 pub mod sdl;
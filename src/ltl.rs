@@ -0,0 +1,241 @@
+//! Larger than Life rules, counting the neighbours within a range `R`.
+//!
+//! Rules are written the way Golly does, e.g. Bosco's Rule:
+//!
+//! * `R5`: neighbours within 5 cells.
+//! * `C0`: number of states, as in Generations rules. 0 and 1 both mean 2.
+//! * `M1`: the cell counts as its own neighbour, `M0` if it doesn't.
+//! * `S34..58`: survival with 34 to 58 alive neighbours.
+//! * `B34..45`: birth with 34 to 45.
+//! * `NM`: Moore neighbourhood, `NN` for von Neumann.
+//!
+//! Counting every neighbour of every cell takes `(2R + 1)^2` steps per cell,
+//! so counts are read from a summed-area table instead: the sum over any
+//! rectangle is 4 lookups. Von Neumann diamonds are summed row by row.
+
+use std::fmt;
+use std::str::FromStr;
+use ndarray::prelude::*;
+
+use cell::Cell;
+use grid::GridItem;
+use neighbourhood::Neighbourhood;
+use topology::Topology;
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LargerThanLife {
+    range: usize,
+    states: usize,
+    middle: bool,
+    survival: (usize, usize),
+    birth: (usize, usize),
+    von_neumann: bool,
+}
+
+impl LargerThanLife {
+    /// A Moore range rule with 2 states, the cell counting as its own
+    /// neighbour.
+    pub fn new(range: usize, birth: (usize, usize), survival: (usize, usize)) -> Self {
+        LargerThanLife {
+            range: range,
+            states: 2,
+            middle: true,
+            survival: survival,
+            birth: birth,
+            von_neumann: false,
+        }
+    }
+
+    /// R5,C0,M1,S34..58,B34..45,NM
+    pub fn bosco() -> Self {
+        LargerThanLife::new(5, (34, 45), (34, 58))
+    }
+
+    pub fn range(&self) -> usize {
+        self.range
+    }
+
+    /// Number of states, alive and empty included.
+    pub fn states(&self) -> usize {
+        self.states
+    }
+
+    /// Whether the cell counts as its own neighbour.
+    pub fn middle(&self) -> bool {
+        self.middle
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        if self.von_neumann {
+            Neighbourhood::VonNeumann(self.range)
+        } else {
+            Neighbourhood::Moore(self.range)
+        }
+    }
+
+    /// Whether a cell dead for `age` generations is still dying.
+    pub fn is_refractory(&self, age: usize) -> bool {
+        age + 2 < self.states
+    }
+
+    /// The next state of the given cell with `count` alive neighbours, the
+    /// cell itself included if the rule says so.
+    pub fn fate<T: GridItem>(&self, cell: T, count: usize) -> T {
+        let is_refractory = cell.age().map_or(false, |x| self.is_refractory(x));
+        let within = |(min, max): (usize, usize)| count >= min && count <= max;
+
+        match cell.is_alive() {
+            true if within(self.survival) => cell.keep(),
+            true                          => cell.kill(),
+            false if is_refractory        => cell.rot(),
+            false if within(self.birth)   => cell.revive(),
+            false                         => cell.rot(),
+        }
+    }
+
+    /// The count of every cell of the grid, with edges joined the given way.
+    pub fn counts(&self, cells: &Array2<Cell>, topology: Topology) -> Array2<usize> {
+        let (n, m) = cells.dim();
+        let r = self.range;
+        let side = 2 * r + 1;
+
+        // The grid with a margin of `r` cells read across the edges, summed
+        // from the top left corner: `sums[[i, j]]` holds the cells above and
+        // left of (i, j), or just left of it for von Neumann rows.
+        let mut sums: Array2<usize> = Array2::zeros((n + 2 * r + 1, m + 2 * r + 1));
+
+        for (i, j) in iproduct!(0..n + 2 * r, 0..m + 2 * r) {
+            let offset = (i as isize - r as isize, j as isize - r as isize);
+            let alive = topology.neighbour((0, 0), offset, (n, m))
+                .map_or(0, |(x, y)| cells[[x, y]].is_alive() as usize);
+
+            let above = if self.von_neumann { 0 } else { sums[[i, j + 1]] - sums[[i, j]] };
+            sums[[i + 1, j + 1]] = sums[[i + 1, j]] + above + alive;
+        }
+
+        Array2::from_shape_fn((n, m), |(x, y)| {
+            let count = if self.von_neumann {
+                (0..side)
+                    .map(|dx| {
+                        let w = r - (dx as isize - r as isize).abs() as usize;
+                        sums[[x + dx + 1, y + r + w + 1]] - sums[[x + dx + 1, y + r - w]]
+                    })
+                    .sum()
+            } else {
+                sums[[x + side, y + side]] + sums[[x, y]] - sums[[x, y + side]] - sums[[x + side, y]]
+            };
+
+            if !self.middle && cells[[x, y]].is_alive() { count - 1 } else { count }
+        })
+    }
+}
+
+
+impl FromStr for LargerThanLife {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<LargerThanLife, String> {
+        let invalid = |part: &str| format!("Invalid rulestring {:?}: unexpected {:?}", s, part);
+        let number = |part: &str| part[1..].parse::<usize>().map_err(|_| invalid(part));
+        let interval = |part: &str| {
+            let bounds: Vec<&str> = part[1..].split("..").collect();
+
+            match (bounds.len(), bounds[0].parse(), bounds.last().unwrap().parse()) {
+                (2, Ok(min), Ok(max)) => Ok((min, max)),
+                _ => Err(invalid(part)),
+            }
+        };
+
+        let parts: Vec<&str> = s.trim().split(',').map(|x| x.trim()).collect();
+
+        if parts.len() != 5 && parts.len() != 6 {
+            return Err(format!("Invalid rulestring {:?}", s));
+        }
+
+        let mut rule = LargerThanLife::new(0, (0, 0), (0, 0));
+
+        for (i, &part) in parts.iter().enumerate() {
+            let prefix = part.chars().next().map(|c| c.to_ascii_uppercase());
+
+            match (i, prefix) {
+                (0, Some('R')) => rule.range = number(part)?,
+                (1, Some('C')) => rule.states = number(part)?.max(2),
+                (2, Some('M')) => rule.middle = match &part[1..] {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(invalid(part)),
+                },
+                (3, Some('S')) => rule.survival = interval(part)?,
+                (4, Some('B')) => rule.birth = interval(part)?,
+                (5, Some('N')) => rule.von_neumann = match &part[1..] {
+                    "M" | "m" => false,
+                    "N" | "n" => true,
+                    _ => return Err(invalid(part)),
+                },
+                _ => return Err(invalid(part)),
+            }
+        }
+
+        if rule.range == 0 {
+            return Err(format!("Invalid rulestring {:?}: range must be at least 1", s));
+        }
+
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for LargerThanLife {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "R{},C{},M{},S{}..{},B{}..{},N{}",
+               self.range,
+               if self.states > 2 { self.states } else { 0 },
+               self.middle as usize,
+               self.survival.0, self.survival.1,
+               self.birth.0, self.birth.1,
+               if self.von_neumann { 'N' } else { 'M' })
+    }
+}
+
+
+#[test]
+fn test_parse() {
+    let rule: LargerThanLife = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+
+    assert_eq!(rule, LargerThanLife::bosco());
+    assert_eq!(rule.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+    assert_eq!("r5,c0,m1,s34..58,b34..45".parse(), Ok(LargerThanLife::bosco()));
+
+    let rule: LargerThanLife = "R2,C3,M0,S2..5,B3..3,NN".parse().unwrap();
+
+    assert_eq!(rule.states(), 3);
+    assert_eq!(rule.neighbourhood(), Neighbourhood::VonNeumann(2));
+    assert_eq!(rule.to_string(), "R2,C3,M0,S2..5,B3..3,NN");
+
+    let xs = vec!["", "B3/S23", "R0,C0,M1,S1..2,B1..2,NM", "R5,C0,M2,S34..58,B34..45,NM",
+                  "R5,C0,M1,S34,B34..45,NM", "R5,C0,M1,B34..45,S34..58,NM",
+                  "R5,C0,M1,S34..58,B34..45,NX", "R5,C0,M1,S34..x,B34..45,NM"];
+
+    for s in xs {
+        assert!(s.parse::<LargerThanLife>().is_err(), "{:?} should not parse", s);
+    }
+}
+
+#[test]
+fn test_counts() {
+    let cells = arr2(&[
+        [Cell::Alive , Cell::Unborn, Cell::Unborn, Cell::Alive ],
+        [Cell::Unborn, Cell::Alive , Cell::Dead(0), Cell::Unborn],
+        [Cell::Unborn, Cell::Unborn, Cell::Alive , Cell::Unborn],
+    ]);
+
+    let moore: LargerThanLife = "R1,C0,M1,S0..9,B0..9,NM".parse().unwrap();
+    let von_neumann: LargerThanLife = "R1,C0,M0,S0..9,B0..9,NN".parse().unwrap();
+
+    assert_eq!(moore.counts(&cells, Topology::Plane),
+               arr2(&[[2, 2, 2, 1], [2, 3, 3, 2], [1, 2, 2, 1]]));
+    // Every column holds one alive cell, each count misses one column.
+    assert_eq!(moore.counts(&cells, Topology::Torus), Array2::from_elem((3, 4), 3));
+    assert_eq!(von_neumann.counts(&cells, Topology::Plane),
+               arr2(&[[0, 2, 1, 0], [2, 0, 2, 1], [0, 2, 0, 1]]));
+}
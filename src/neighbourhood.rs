@@ -0,0 +1,148 @@
+//! Which cells around a cell count as its neighbours.
+//!
+//! Neighbourhoods are sets of offsets, as (rows, columns), from the cell.
+//! Hexagonal grids are drawn on square ones by leaving out the top right and
+//! bottom left corners of the Moore neighbourhood, the way Golly does.
+//!
+//! Counts above 8 are given to `Rule::new`, as rulestrings only write up to
+//! 8. Rules tell apart counts up to `rule::MAX_COUNT`, cells with more alive
+//! neighbours than that die or stay empty.
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// Cells within `r` rows and columns, 8 for `Moore(1)`.
+    Moore(usize),
+    /// Cells within `r` steps along rows and columns, 4 for `VonNeumann(1)`.
+    VonNeumann(usize),
+    /// The 6 neighbours of a hexagonal grid.
+    Hexagonal,
+    /// Cells within `r` of the cell in its own row or column.
+    Cross(usize),
+    /// Any offsets, the cell itself included if it's listed.
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Neighbourhood {
+    /// Offsets of the neighbours, in reading order for the built in ones.
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        let r = self.radius() as isize;
+        let around = iproduct!(-r..r + 1, -r..r + 1).filter(|&x| x != (0, 0));
+
+        match *self {
+            Neighbourhood::Moore(_) => around.collect(),
+            Neighbourhood::VonNeumann(_) => around.filter(|&(x, y)| x.abs() + y.abs() <= r).collect(),
+            Neighbourhood::Hexagonal => around.filter(|&(x, y)| x * y >= 0).collect(),
+            Neighbourhood::Cross(_) => around.filter(|&(x, y)| x == 0 || y == 0).collect(),
+            Neighbourhood::Custom(ref xs) => xs.clone(),
+        }
+    }
+
+    /// The furthest a neighbour is along rows or columns.
+    pub fn radius(&self) -> usize {
+        match *self {
+            Neighbourhood::Moore(r) | Neighbourhood::VonNeumann(r) | Neighbourhood::Cross(r) => r,
+            Neighbourhood::Hexagonal => 1,
+            Neighbourhood::Custom(ref xs) => {
+                xs.iter().map(|&(x, y)| x.abs().max(y.abs()) as usize).max().unwrap_or(0)
+            }
+        }
+    }
+}
+
+impl Default for Neighbourhood {
+    fn default() -> Self {
+        Neighbourhood::Moore(1)
+    }
+}
+
+
+#[test]
+fn test_offsets() {
+    let xs = vec![
+        (Neighbourhood::Moore(1), 8, 1),
+        (Neighbourhood::Moore(2), 24, 2),
+        (Neighbourhood::VonNeumann(1), 4, 1),
+        (Neighbourhood::VonNeumann(2), 12, 2),
+        (Neighbourhood::Hexagonal, 6, 1),
+        (Neighbourhood::Cross(3), 12, 3),
+        (Neighbourhood::Custom(vec![(0, 0), (-2, 1)]), 2, 2),
+    ];
+
+    for (neighbourhood, len, radius) in xs {
+        assert_eq!(neighbourhood.offsets().len(), len, "{:?}", neighbourhood);
+        assert_eq!(neighbourhood.radius(), radius, "{:?}", neighbourhood);
+    }
+
+    assert!(!Neighbourhood::Hexagonal.offsets().contains(&(-1, 1)));
+    assert!(!Neighbourhood::Hexagonal.offsets().contains(&(1, -1)));
+    assert_eq!(Neighbourhood::default().offsets().last(), Some(&(1, 1)));
+}
+
+#[test]
+fn test_growth() {
+    use cell::Cell;
+    use colony::Colony;
+    use community::Community;
+    use coord::Point;
+    use grid::{ Grid, GridItem };
+    use ndarray::arr2;
+    use pattern::{ Custom, Layout };
+    use population::Population;
+
+    // Every cell next to an alive one is born, and none die: a single cell
+    // grows into the neighbourhood twice over.
+    let rule = "B12345678/S012345678".parse().unwrap();
+    let xs = vec![
+        (Neighbourhood::Moore(1), 25),
+        (Neighbourhood::VonNeumann(1), 13),
+        (Neighbourhood::Hexagonal, 19),
+        (Neighbourhood::Cross(1), 13),
+        (Neighbourhood::Custom(vec![(0, 1), (1, 0)]), 6),
+    ];
+
+    for (neighbourhood, expected) in xs {
+        let mut population = Population::empty(11)
+            .with_rule(rule)
+            .with_neighbourhood(neighbourhood.clone());
+        let mut community = Community::empty(11)
+            .with_rule(rule)
+            .with_neighbourhood(neighbourhood.clone());
+        let mut colony = Colony::new(vec![Point(5, 5)], 1)
+            .with_rule(rule)
+            .with_neighbourhood(neighbourhood.clone());
+
+        population.regenerate((5, 5));
+        community.insert(Layout::new((5, 5), Custom(arr2(&[[Cell::Alive]]))));
+
+        for _ in 0..2 {
+            population = population.evolve();
+            community = community.evolve();
+            colony = colony.evolve();
+        }
+
+        let alive = population.cells().iter().filter(|x| x.is_alive()).count();
+
+        assert_eq!(alive, expected, "{:?}", neighbourhood);
+        assert_eq!(format!("{}", population), format!("{}", community), "{:?}", neighbourhood);
+        assert_eq!(colony.population(), expected, "{:?}", neighbourhood);
+    }
+}
+
+#[test]
+fn test_large_counts() {
+    use colony::Colony;
+    use coord::Point;
+    use grid::Grid;
+    use rule::Rule;
+
+    // Only the middle of a 5 x 5 square has all of its 24 neighbours alive.
+    let cells = iproduct!(0..5, 0..5).map(|(x, y)| Point(x, y));
+    let grid = Colony::new(cells, 1)
+        .with_rule(Rule::new(&[], &[24]))
+        .with_neighbourhood(Neighbourhood::Moore(2))
+        .evolve();
+
+    assert_eq!(grid.population(), 1);
+    assert_eq!(grid.bounds(), Some((Point(2, 2), Point(2, 2))));
+}
@@ -1,4 +1,5 @@
 use ndarray::prelude::*;
+use rand::{ Rng, SeedableRng, XorShiftRng };
 
 use cell::Cell;
 
@@ -44,9 +45,91 @@ impl<T: Pattern<Matrix>> Layout<T> {
 // Custom
 
 
+/// Rows and columns of cells, each alive with even odds.
 #[derive(Debug, Clone)]
 pub struct Random(pub usize, pub usize);
 
+impl Random {
+    /// The same cells on every call and platform.
+    pub fn with_seed(self, seed: u64) -> Noise {
+        Noise::from(self).with_seed(seed)
+    }
+
+    /// Probability of a cell being alive, between 0 and 1.
+    pub fn with_density(self, density: f64) -> Noise {
+        Noise::from(self).with_density(density)
+    }
+
+    /// Draws the cells from the given generator, see `Noise::fill`.
+    pub fn fill<R: Rng>(&self, rng: &mut R) -> Matrix {
+        Noise::from(self.clone()).fill(rng)
+    }
+}
+
+/// Cells alive with a given probability, half of them by default. Seeded
+/// patterns are the same on every call and platform.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    size: (usize, usize),
+    density: f64,
+    seed: Option<u64>,
+}
+
+impl Noise {
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Probability of a cell being alive, between 0 and 1.
+    pub fn with_density(mut self, density: f64) -> Self {
+        self.density = density.max(0.0).min(1.0);
+        self
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn density(&self) -> f64 {
+        self.density
+    }
+
+    /// Draws the cells from the given generator, row by row. Only integers
+    /// are drawn so the result doesn't depend on floating point rounding.
+    pub fn fill<R: Rng>(&self, rng: &mut R) -> Matrix {
+        let threshold = (self.density * (1u64 << 32) as f64) as u64;
+
+        Array2::from_shape_fn(self.size, |_| Cell::from((rng.next_u32() as u64) < threshold))
+    }
+}
+
+impl From<Random> for Noise {
+    fn from(random: Random) -> Self {
+        Noise {
+            size: (random.0, random.1),
+            density: 0.5,
+            seed: None,
+        }
+    }
+}
+
+/// A generator giving the same numbers for the same seed everywhere.
+pub fn seeded(seed: u64) -> XorShiftRng {
+    let (lo, hi) = (seed as u32, (seed >> 32) as u32);
+
+    // XorShift needs a seed other than zero.
+    XorShiftRng::from_seed([lo, hi, lo ^ 0x9e37_79b9, hi ^ 0x7f4a_7c15])
+}
+
+/// Any arrangement of cells, e.g. read from a pattern file.
+#[derive(Debug, Clone)]
+pub struct Custom(pub Matrix);
+
 
 // Still lifes
 
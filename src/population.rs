@@ -2,10 +2,15 @@
 
 use std::fmt;
 use itertools::Itertools;
+use ndarray::prelude::*;
 
 use coord::Dim2 as Coord;
 use cell::Cell;
-use grid::{ Grid, GridItem, inc, dec };
+use grid::Grid;
+use pattern::{ Pattern, Layout };
+use neighbourhood::Neighbourhood;
+use rule::Rule;
+use topology::Topology;
 
 
 #[derive(Debug, Clone, Eq)]
@@ -13,21 +18,55 @@ pub struct Population {
     cells: Vec<Cell>,
     size: (usize, usize),
     gen: usize,
+    rule: Rule,
+    topology: Topology,
+    neighbourhood: Neighbourhood,
 }
 
 impl Population {
+    /// A square grid.
     pub fn new(cells: Vec<Cell>, gen: usize) -> Self {
         let size = f32::sqrt(cells.len() as f32) as usize;
 
+        Population::from_shape_vec((size, size), cells, gen)
+    }
+
+    /// A grid of the given rows and columns, with cells stored row by row.
+    pub fn from_shape_vec(size: (usize, usize), cells: Vec<Cell>, gen: usize) -> Self {
         Population {
             cells: cells,
-            size: (size, size),
-            gen: gen
+            size: size,
+            gen: gen,
+            rule: Rule::default(),
+            topology: Topology::default(),
+            neighbourhood: Neighbourhood::default(),
         }
     }
 
+    /// Evolves with the given rule instead of Conway's.
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    /// Joins the edges the given way instead of as a torus.
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Counts the given neighbours instead of the 8 around each cell.
+    pub fn with_neighbourhood(mut self, neighbourhood: Neighbourhood) -> Self {
+        self.neighbourhood = neighbourhood;
+        self
+    }
+
     pub fn empty(size: usize) -> Self {
-        vec![false; size * size].into()
+        Population::rectangle(size, size)
+    }
+
+    pub fn rectangle(rows: usize, cols: usize) -> Self {
+        Population::from_shape_vec((rows, cols), vec![Cell::Unborn; rows * cols], 1)
     }
 
     pub fn cells(&self) -> &Vec<Cell> {
@@ -37,8 +76,22 @@ impl Population {
     pub fn regenerate<T: Into<Coord>>(&mut self, coord: T) {
         let coord: Coord = coord.into();
         let (x, y) = coord.into();
-        let size = self.size();
-        self.cells[x * size + y] = Cell::Alive;
+        self.cells[x * self.size.1 + y] = Cell::Alive;
+    }
+
+    /// Mix in a pattern
+    pub fn insert<T: Pattern<Array2<Cell>>>(&mut self, layout: Layout<T>) {
+        let (x, y) = layout.offset();
+        let (n, m) = layout.size();
+
+        if n + x > self.size.0 || m + y > self.size.1 {
+            panic!("Pattern size {:?} with offset {:?} overflows grid of {:?}",
+                   layout.size(), layout.offset(), self.size);
+        }
+
+        for ((i, j), &cell) in layout.pattern().indexed_iter() {
+            self.cells[(x + i) * self.size.1 + y + j] = cell;
+        }
     }
 
     pub fn generation(&self) -> usize {
@@ -54,7 +107,7 @@ impl IntoIterator for Population {
     fn into_iter(self) -> Self::IntoIter {
         self.cells.iter()
             .enumerate()
-            .map(|(i, &x)| (Coord::from_index(i, self.size()), x))
+            .map(|(i, &x)| (Coord::from_row_major(i, self.size.1), x))
             .collect::<Vec<(Coord, Cell)>>()
             .into_iter()
     }
@@ -65,43 +118,41 @@ impl Grid for Population {
     type Cell = Cell;
     type Coord = Coord;
 
-    fn size(&self) -> usize {
-        self.size.0
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+
+    fn rule(&self) -> Rule {
+        self.rule
     }
 
 
     fn item(&self, coord: Coord) -> Cell {
         let (x, y) = coord.into();
-        self.cells[x * self.size() + y]
+        self.cells[x * self.size.1 + y]
     }
 
 
     fn item_neighbours(&self, coord: Coord) -> Vec<Cell> {
-        let (x, y) = coord.into();
-        let size = self.size();
-
-        [(x, dec(y, size)),
-         (x, inc(y, size)),
-         (dec(x, size), y),
-         (inc(x, size), y),
-         (dec(x, size), dec(y, size)),
-         (inc(x, size), dec(y, size)),
-         (dec(x, size), inc(y, size)),
-         (inc(x, size), inc(y, size))]
+        self.topology.neighbours(coord.into(), &self.neighbourhood.offsets(), self.size)
             .into_iter()
-            .map(|&x| self.item(x.into()))
+            .map(|x| x.map_or(Cell::Unborn, |x| self.item(x.into())))
             .collect()
     }
 
     fn evolve(&self) -> Self {
         let mut vec = vec![];
-        let size = self.size();
+        let (n, m) = self.size;
 
-        for coord in iproduct!(0..size, 0..size) {
+        for coord in iproduct!(0..n, 0..m) {
             vec.push(self.item_fate(coord.into()));
         }
 
-        Population::new(vec, self.gen + 1)
+        Population::from_shape_vec(self.size, vec, self.gen + 1)
+            .with_rule(self.rule)
+            .with_topology(self.topology)
+            .with_neighbourhood(self.neighbourhood.clone())
     }
 }
 
@@ -111,7 +162,7 @@ impl fmt::Display for Population {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let res = self.cells.clone().into_iter()
             .map(|x| format!("{}", x))
-            .chunks(self.size())
+            .chunks(self.size.1)
             .into_iter()
             .map(|xs| format!("{}\n", xs.collect::<String>()))
             .collect::<String>();
@@ -220,6 +271,8 @@ pub fn glider_tr(mut population: Population, offset: (usize, usize)) -> Populati
 
 #[test]
 fn test_glider() {
+    use grid::GridItem;
+
     let size = 5;
     let offset = (1, 1);
     let ppl = vec![false; size * size];
@@ -263,8 +316,55 @@ fn test_fate() {
 }
 
 
+#[test]
+fn test_fate_with_rule() {
+    let size = 5;
+    let offset = (1, 1);
+    let rule = "B3/S012345678".parse().unwrap();
+    let ppl = glider(Population::empty(size).with_rule(rule), offset);
+
+    let xs = vec![
+        ((1, 2), Cell::Alive),
+        ((2, 1), Cell::Alive),
+        ((0, 0), Cell::Unborn),
+    ];
+
+    for ((x, y), expected) in xs {
+        assert_eq!(ppl.item_fate((x, y).into()), expected);
+    }
+}
+
+
+#[test]
+fn test_evolve_generations() {
+    use grid::GridItem;
+
+    let mut ppl = Population::empty(5).with_rule("B2/S/C3".parse().unwrap());
+    ppl.regenerate((2, 1));
+    ppl.regenerate((2, 2));
+
+    let gen1 = ppl.evolve();
+    let gen2 = gen1.evolve();
+
+    let xs = vec![
+        (&gen1, (2, 1), Cell::Dead(0)),
+        (&gen1, (1, 1), Cell::Alive),
+        (&gen1, (3, 2), Cell::Alive),
+        (&gen2, (2, 1), Cell::Dead(1)),
+        (&gen2, (1, 1), Cell::Dead(0)),
+    ];
+
+    for (ppl, (x, y), expected) in xs {
+        assert_eq!(ppl.item((x, y).into()).age(), expected.age());
+        assert_eq!(ppl.item((x, y).into()), expected);
+    }
+}
+
+
 #[test]
 fn test_neigbours() {
+    use grid::GridItem;
+
     let size = 5;
     let offset = (1, 1);
     let ppl = glider(vec![false; size * size].into(), offset);
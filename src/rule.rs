@@ -0,0 +1,644 @@
+//! Life-like rules. A rule decides the fate of a cell given the number of
+//! alive neighbours it has.
+//!
+//! Rules are written as rulestrings:
+//!
+//! * `B3/S23`: Birth with 3 neighbours, survival with 2 or 3 (Conway).
+//! * `B36/S23`: HighLife.
+//! * `B2/S`: Seeds, no cell survives.
+//! * `23/3`: Legacy notation, survival first then birth.
+//!
+//! Generations rules add the number of states a cell goes through, counting
+//! alive and empty. A dying cell is refractory: it can't be born again until
+//! it has decayed to empty.
+//!
+//! * `B2/S/C3`: Brian's Brain.
+//! * `/2/3`: Brian's Brain in legacy notation.
+//! * `345/2/4`: Star Wars.
+//!
+//! Isotropic non-totalistic rules also look at where the alive neighbours
+//! are, using Hensel's letters after each count. The letters list the
+//! configurations the count applies to, or those it doesn't after a `-`:
+//!
+//! * `B2-a/S12`: birth on 2 neighbours, unless they touch each other
+//!   diagonally around a corner.
+//! * `B3/S2-i34q`: tlife.
+//!
+//! A configuration has a bit for each neighbour in reading order, from the
+//! top left one (1) to the bottom right one (128), without the cell itself.
+//!
+//! Rules built with `Rule::new` can also use counts above 8, up to
+//! `MAX_COUNT`, for neighbourhoods of more than 8 cells. Rulestrings only
+//! write counts up to 8.
+
+use std::fmt;
+use std::str::FromStr;
+
+use grid::GridItem;
+
+
+/// Hensel letters with a configuration of each, for up to 4 neighbours. The
+/// letters of 5 to 7 neighbours stand for the complements of the ones of 3 to
+/// 1.
+const LETTERS: [&'static [(char, u8)]; 5] = [
+    &[],
+    &[('c', 1), ('e', 2)],
+    &[('c', 5), ('e', 10), ('a', 3), ('i', 24), ('k', 17), ('n', 36)],
+    &[('c', 37), ('e', 26), ('a', 11), ('i', 7), ('k', 50),
+      ('n', 13), ('j', 14), ('q', 38), ('r', 25), ('y', 49)],
+    &[('c', 165), ('e', 90), ('a', 15), ('i', 29), ('k', 51), ('n', 39), ('j', 58),
+      ('q', 54), ('r', 27), ('t', 57), ('w', 46), ('y', 53), ('z', 60)],
+];
+
+/// Where each neighbour goes when the configuration is turned clockwise, or
+/// mirrored left to right.
+const ROTATE: [usize; 8] = [2, 4, 7, 1, 6, 0, 3, 5];
+const MIRROR: [usize; 8] = [2, 1, 0, 4, 3, 7, 6, 5];
+
+/// The highest neighbour count a rule tells apart, enough for the 224
+/// neighbours of a Moore neighbourhood of radius 7.
+pub const MAX_COUNT: usize = 255;
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rule {
+    birth: Configurations,
+    survival: Configurations,
+    states: usize,
+    // Counts applying to every configuration, looked up on every cell.
+    born: Counts,
+    survive: Counts,
+    totalistic: bool,
+}
+
+impl Rule {
+    /// Counts above `MAX_COUNT` are left out.
+    pub fn new(birth: &[usize], survival: &[usize]) -> Self {
+        let configurations = |xs: &[usize]| {
+            let mut res = Configurations::default();
+
+            for &n in xs.iter().filter(|&&n| n < 9) {
+                res.insert_count(n);
+            }
+
+            res
+        };
+
+        let mut rule = Rule::isotropic(configurations(birth), configurations(survival), 2);
+
+        for &n in birth.iter().filter(|&&n| n > 8 && n <= MAX_COUNT) {
+            rule.born.insert(n);
+        }
+
+        for &n in survival.iter().filter(|&&n| n > 8 && n <= MAX_COUNT) {
+            rule.survive.insert(n);
+        }
+
+        rule
+    }
+
+    fn isotropic(birth: Configurations, survival: Configurations, states: usize) -> Self {
+        let mut rule = Rule {
+            birth: birth,
+            survival: survival,
+            states: states,
+            born: Counts::default(),
+            survive: Counts::default(),
+            totalistic: true,
+        };
+
+        for n in 0..9 {
+            if birth.contains_count(n) {
+                rule.born.insert(n);
+            }
+
+            if survival.contains_count(n) {
+                rule.survive.insert(n);
+            }
+
+            let whole = |x: &Configurations| x.count(n) == 0 || x.contains_count(n);
+            rule.totalistic &= whole(&birth) && whole(&survival);
+        }
+
+        rule
+    }
+
+    /// A Generations rule where cells go through `states` states, alive and
+    /// empty included.
+    pub fn generations(birth: &[usize], survival: &[usize], states: usize) -> Self {
+        let mut rule = Rule::new(birth, survival);
+        rule.states = if states < 2 { 2 } else { states };
+
+        rule
+    }
+
+    /// B3/S23
+    pub fn conway() -> Self {
+        Rule::new(&[3], &[2, 3])
+    }
+
+    /// Neighbour counts giving birth, whatever the configuration.
+    pub fn birth(&self) -> Vec<usize> {
+        (0..MAX_COUNT + 1).filter(|&x| self.is_born(x)).collect()
+    }
+
+    /// Neighbour counts allowing survival, whatever the configuration.
+    pub fn survival(&self) -> Vec<usize> {
+        (0..MAX_COUNT + 1).filter(|&x| self.survives(x)).collect()
+    }
+
+    /// Number of states, 2 for Life-like rules.
+    pub fn states(&self) -> usize {
+        self.states
+    }
+
+    /// Whether the fate of a cell only depends on how many neighbours are
+    /// alive, not where they are.
+    pub fn is_totalistic(&self) -> bool {
+        self.totalistic
+    }
+
+    /// Whether a cell dead for `age` generations is still dying.
+    pub fn is_refractory(&self, age: usize) -> bool {
+        age + 2 < self.states
+    }
+
+    /// Whether a non alive cell with `count` alive neighbours is born, in
+    /// every configuration.
+    pub fn is_born(&self, count: usize) -> bool {
+        self.born.contains(count)
+    }
+
+    /// Whether an alive cell with `count` alive neighbours survives, in
+    /// every configuration.
+    pub fn survives(&self, count: usize) -> bool {
+        self.survive.contains(count)
+    }
+
+    /// Whether a non alive cell with the given neighbours alive is born.
+    pub fn is_born_in(&self, configuration: u8) -> bool {
+        self.birth.contains(configuration)
+    }
+
+    /// Whether an alive cell with the given neighbours alive survives.
+    pub fn survives_in(&self, configuration: u8) -> bool {
+        self.survival.contains(configuration)
+    }
+
+    /// The next state of the given cell with `count` alive neighbours. Only
+    /// meaningful for totalistic rules.
+    pub fn fate<T: GridItem>(&self, cell: T, count: usize) -> T {
+        let born = self.is_born(count);
+        let survives = self.survives(count);
+
+        self.decide(cell, born, survives)
+    }
+
+    /// The next state of the given cell with the given neighbours alive, see
+    /// `configuration`.
+    pub fn configuration_fate<T: GridItem>(&self, cell: T, configuration: u8) -> T {
+        let born = self.is_born_in(configuration);
+        let survives = self.survives_in(configuration);
+
+        self.decide(cell, born, survives)
+    }
+
+    fn decide<T: GridItem>(&self, cell: T, born: bool, survives: bool) -> T {
+        let is_refractory = cell.age().map_or(false, |x| self.is_refractory(x));
+
+        match cell.is_alive() {
+            true if survives       => cell.keep(),
+            true                   => cell.kill(),
+            false if is_refractory => cell.rot(),
+            false if born          => cell.revive(),
+            false                  => cell.rot(),
+        }
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::conway()
+    }
+}
+
+
+/// The configuration of the 8 neighbours of a cell, given in reading order.
+pub fn configuration<T: GridItem>(neighbours: &[T]) -> u8 {
+    neighbours.iter()
+        .take(8)
+        .enumerate()
+        .filter(|&(_, x)| x.is_alive())
+        .fold(0, |acc, (i, _)| acc | 1 << i)
+}
+
+
+/// A set of neighbour configurations, a bit each.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct Configurations([u64; 4]);
+
+impl Configurations {
+    fn contains(&self, x: u8) -> bool {
+        self.0[x as usize / 64] & 1 << (x % 64) != 0
+    }
+
+    fn insert(&mut self, x: u8) {
+        self.0[x as usize / 64] |= 1 << (x % 64);
+    }
+
+    /// Number of configurations with `n` alive neighbours in the set.
+    fn count(&self, n: usize) -> usize {
+        (0..256).filter(|&x| alive(x as u8) == n && self.contains(x as u8)).count()
+    }
+
+    fn insert_count(&mut self, n: usize) {
+        for x in (0..256).map(|x| x as u8).filter(|&x| alive(x) == n) {
+            self.insert(x);
+        }
+    }
+
+    fn contains_count(&self, n: usize) -> bool {
+        (0..256).map(|x| x as u8).filter(|&x| alive(x) == n).all(|x| self.contains(x))
+    }
+}
+
+/// A set of neighbour counts up to `MAX_COUNT`, a bit each.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct Counts([u64; 4]);
+
+impl Counts {
+    fn contains(&self, n: usize) -> bool {
+        n <= MAX_COUNT && self.0[n / 64] & 1 << (n % 64) != 0
+    }
+
+    fn insert(&mut self, n: usize) {
+        self.0[n / 64] |= 1 << (n % 64);
+    }
+}
+
+fn alive(x: u8) -> usize {
+    x.count_ones() as usize
+}
+
+/// Hensel letters for `n` alive neighbours, with a configuration of each.
+fn letters(n: usize) -> Vec<(char, u8)> {
+    if n <= 4 {
+        LETTERS[n].to_vec()
+    } else {
+        LETTERS[8 - n].iter().map(|&(c, x)| (c, !x)).collect()
+    }
+}
+
+/// The configuration turned and mirrored every way.
+fn orientations(x: u8) -> Vec<u8> {
+    let permute = |x: u8, to: &[usize; 8]| {
+        (0..8).filter(|&i| x & 1 << i != 0).fold(0, |acc, i| acc | 1 << to[i])
+    };
+
+    (0..4)
+        .scan(x, |x, _| {
+            let res = *x;
+            *x = permute(*x, &ROTATE);
+            Some(res)
+        })
+        .flat_map(|x| vec![x, permute(x, &MIRROR)])
+        .collect()
+}
+
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rule, String> {
+        let parts: Vec<&str> = s.trim().split('/').collect();
+
+        if parts.len() != 2 && parts.len() != 3 {
+            return Err(format!("Invalid rulestring {:?}", s));
+        }
+
+        let (birth, survival) = match (prefix(parts[0]), prefix(parts[1])) {
+            (Some('b'), Some('s')) => (&parts[0][1..], &parts[1][1..]),
+            (Some('s'), Some('b')) => (&parts[1][1..], &parts[0][1..]),
+            // Legacy S/B notation.
+            (None, None) => (parts[1], parts[0]),
+            _ => return Err(format!("Invalid rulestring {:?}", s)),
+        };
+
+        let states = match parts.get(2) {
+            Some(x) => states(x, s)?,
+            None => 2,
+        };
+
+        Ok(Rule::isotropic(configurations(birth, s)?, configurations(survival, s)?, states))
+    }
+}
+
+fn prefix(s: &str) -> Option<char> {
+    match s.chars().next() {
+        Some(c) if c == 'B' || c == 'b' => Some('b'),
+        Some(c) if c == 'S' || c == 's' => Some('s'),
+        _ => None,
+    }
+}
+
+fn states(s: &str, rule: &str) -> Result<usize, String> {
+    let n = if s.starts_with('C') || s.starts_with('c') { &s[1..] } else { s };
+
+    match n.parse() {
+        Ok(x) if x >= 2 => Ok(x),
+        _ => Err(format!("Invalid rulestring {:?}: bad number of states {:?}", rule, s)),
+    }
+}
+
+/// Counts, each followed by the Hensel letters it applies to if it doesn't
+/// apply to every configuration.
+fn configurations(s: &str, rule: &str) -> Result<Configurations, String> {
+    let unexpected = |c: char| format!("Invalid rulestring {:?}: unexpected {:?}", rule, c);
+    let mut res = Configurations::default();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let n = match c.to_digit(10) {
+            Some(n) if n < 9 => n as usize,
+            _ => return Err(unexpected(c)),
+        };
+
+        let negated = chars.peek() == Some(&'-');
+
+        if negated {
+            chars.next();
+        }
+
+        let table = letters(n);
+        let mut chosen = vec![];
+
+        while let Some(&c) = chars.peek() {
+            if !c.is_alphabetic() {
+                break;
+            }
+
+            if !table.iter().any(|&(x, _)| x == c) {
+                return Err(unexpected(c));
+            }
+
+            chosen.push(c);
+            chars.next();
+        }
+
+        if chosen.is_empty() {
+            if negated {
+                return Err(unexpected('-'));
+            }
+
+            res.insert_count(n);
+            continue;
+        }
+
+        for &(_, x) in table.iter().filter(|&&(c, _)| chosen.contains(&c) != negated) {
+            for x in orientations(x) {
+                res.insert(x);
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hensel = |xs: &Configurations| {
+            (0..9)
+                .filter(|&n| xs.count(n) > 0)
+                .map(|n| {
+                    if xs.contains_count(n) {
+                        return n.to_string();
+                    }
+
+                    let table = letters(n);
+                    let with: String = table.iter()
+                        .filter(|&&(_, x)| xs.contains(x))
+                        .map(|x| x.0)
+                        .collect();
+                    let without: String = table.iter()
+                        .filter(|&&(_, x)| !xs.contains(x))
+                        .map(|x| x.0)
+                        .collect();
+
+                    if with.len() <= without.len() {
+                        format!("{}{}", n, with)
+                    } else {
+                        format!("{}-{}", n, without)
+                    }
+                })
+                .collect::<String>()
+        };
+
+        write!(f, "B{}/S{}", hensel(&self.birth), hensel(&self.survival))?;
+
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[test]
+fn test_parse() {
+    let xs = vec![
+        ("B3/S23", Rule::new(&[3], &[2, 3])),
+        ("b36/s23", Rule::new(&[3, 6], &[2, 3])),
+        ("S23/B3", Rule::new(&[3], &[2, 3])),
+        ("B2/S", Rule::new(&[2], &[])),
+        ("23/3", Rule::new(&[3], &[2, 3])),
+        ("/2", Rule::new(&[2], &[])),
+        ("B2/S/C3", Rule::generations(&[2], &[], 3)),
+        ("/2/3", Rule::generations(&[2], &[], 3)),
+        ("345/2/4", Rule::generations(&[2], &[3, 4, 5], 4)),
+        ("B3/S23/2", Rule::conway()),
+    ];
+
+    for (s, expected) in xs {
+        assert_eq!(s.parse::<Rule>(), Ok(expected));
+    }
+}
+
+#[test]
+fn test_parse_invalid() {
+    let xs = vec!["", "B3", "B3/S2/1", "B3/S2/C", "B3/S/3/4", "B9/S23", "B3/23", "Bx/S23"];
+
+    for s in xs {
+        assert!(s.parse::<Rule>().is_err(), "{:?} should not parse", s);
+    }
+}
+
+#[test]
+fn test_display() {
+    let xs = vec![
+        ("23/3", "B3/S23"),
+        ("B3678/S34678", "B3678/S34678"),
+        ("B2/S", "B2/S"),
+        ("345/2/4", "B2/S345/C4"),
+    ];
+
+    for (s, expected) in xs {
+        assert_eq!(s.parse::<Rule>().unwrap().to_string(), expected);
+    }
+}
+
+#[test]
+fn test_fate_generations() {
+    use cell::Cell;
+
+    let rule: Rule = "/2/4".parse().unwrap();
+    let xs = vec![
+        (Cell::Alive, 2, Cell::Dead(0)),
+        (Cell::Dead(0), 2, Cell::Dead(1)),
+        (Cell::Dead(1), 2, Cell::Dead(2)),
+        (Cell::Dead(2), 2, Cell::Alive),
+        (Cell::Dead(2), 1, Cell::Dead(3)),
+        (Cell::Unborn, 2, Cell::Alive),
+    ];
+
+    for (cell, count, expected) in xs {
+        assert_eq!(rule.fate(cell, count).age(), expected.age());
+        assert_eq!(rule.fate(cell, count), expected);
+    }
+}
+
+#[test]
+fn test_counts() {
+    let rule = Rule::new(&[3, 9, 12, MAX_COUNT + 1], &[2, 24, MAX_COUNT]);
+
+    assert_eq!(rule.birth(), vec![3, 9, 12]);
+    assert_eq!(rule.survival(), vec![2, 24, MAX_COUNT]);
+    assert!(rule.is_born(12) && !rule.is_born(13));
+    assert!(!rule.is_born(MAX_COUNT + 1) && !rule.survives(1000));
+    assert!(rule.is_totalistic());
+    assert_eq!(rule.to_string(), "B3/S2");
+    assert!(rule != Rule::new(&[3], &[2]));
+}
+
+#[test]
+fn test_hensel() {
+    let xs = vec![
+        ("B2-a/S12", "B2-a/S12"),
+        ("B3/S2-i34q", "B3/S2-i34q"),
+        ("B2ce3-ir/S23-a", "B2ce3-ir/S23-a"),
+        ("B3ceaiknjqry/S2ceaikn3", "B3/S23"),
+        ("B2ceaik/S", "B2-n/S"),
+        ("B4cetwz5-c/S/C3", "B4cetwz5-c/S/C3"),
+    ];
+
+    for (s, expected) in xs {
+        assert_eq!(s.parse::<Rule>().map(|x| x.to_string()), Ok(expected.to_string()));
+    }
+
+    assert_eq!("B3ceaiknjqry/S2ceaikn3".parse(), Ok(Rule::conway()));
+    assert!(Rule::conway().is_totalistic());
+    assert!(!"B2-a/S12".parse::<Rule>().unwrap().is_totalistic());
+
+    for s in vec!["B2z/S23", "B1-/S23", "B0c/S", "B3/S2-i-a", "B3/S2I"] {
+        assert!(s.parse::<Rule>().is_err(), "{:?} should not parse", s);
+    }
+}
+
+#[test]
+fn test_letters() {
+    // Each letter stands for a different set of configurations, and together
+    // they cover every configuration with that many neighbours.
+    for n in 1..8 {
+        let mut seen = Configurations::default();
+
+        for (c, x) in letters(n) {
+            let mut xs = orientations(x);
+            xs.sort();
+            xs.dedup();
+
+            assert_eq!(alive(x), n);
+            assert!(xs.iter().all(|&x| !seen.contains(x)), "{}{}", n, c);
+
+            for x in xs {
+                seen.insert(x);
+            }
+        }
+
+        assert!(seen.contains_count(n), "{} neighbours", n);
+        assert_eq!(seen.count(n), (0..256).filter(|&x| alive(x as u8) == n).count());
+    }
+}
+
+#[test]
+fn test_configuration_fate() {
+    use cell::Cell;
+
+    let rule: Rule = "B2-a/S12".parse().unwrap();
+    let cells = |xs: &[usize]| {
+        (0..8).map(|i| if xs.contains(&i) { Cell::Alive } else { Cell::Unborn }).collect::<Vec<_>>()
+    };
+
+    // Top left and top: 2a, touching around the corner.
+    assert_eq!(configuration(&cells(&[0, 1])), 3);
+    assert_eq!(rule.configuration_fate(Cell::Unborn, configuration(&cells(&[0, 1]))), Cell::Unborn);
+    // Left and right: 2i.
+    assert_eq!(rule.configuration_fate(Cell::Unborn, configuration(&cells(&[3, 4]))), Cell::Alive);
+    // Bottom and bottom right: 2a again, turned.
+    assert_eq!(rule.configuration_fate(Cell::Unborn, configuration(&cells(&[6, 7]))), Cell::Unborn);
+    assert_eq!(rule.configuration_fate(Cell::Alive, configuration(&cells(&[6, 7]))), Cell::Alive);
+}
+
+#[test]
+fn test_isotropic_grids() {
+    use cell::Cell;
+    use colony::Colony;
+    use community::Community;
+    use coord::Point;
+    use grid::Grid;
+    use hashlife::HashLife;
+    use pattern::{ Layout, Random };
+    use population::Population;
+
+    let rule: Rule = "B2ce3-ir/S23-a".parse().unwrap();
+    let soup = Random(8, 8).with_seed(3).with_density(0.5);
+    let mut community = Community::empty(24).with_rule(rule);
+    community.insert(Layout::new((8, 8), soup));
+
+    let cells: Vec<Cell> = community.clone().into_iter().map(|(_, x)| x).collect();
+    let alive: Vec<Point> = community.clone().into_iter()
+        .filter(|&(_, x)| x.is_alive())
+        .map(|(coord, _)| coord.into())
+        .collect();
+
+    let mut population = Population::from(cells).with_rule(rule);
+    let mut colony = Colony::new(alive.clone(), 1).with_rule(rule);
+    let mut hashlife = HashLife::empty().with_rule(rule);
+
+    for x in alive {
+        hashlife.regenerate(x);
+    }
+
+    // Too few generations to reach the edges of the torus.
+    for _ in 0..3 {
+        community = community.evolve();
+        population = population.evolve();
+        colony = colony.evolve();
+        hashlife = hashlife.evolve();
+    }
+
+    let points = |xs: Vec<(Point, bool)>| {
+        let mut xs: Vec<Point> = xs.into_iter().filter(|x| x.1).map(|x| x.0).collect();
+        xs.sort();
+        xs
+    };
+
+    let expected = points(community.clone().into_iter()
+        .map(|(coord, x)| (coord.into(), x.is_alive()))
+        .collect());
+
+    assert!(!expected.is_empty());
+    assert_eq!(format!("{}", community), format!("{}", population));
+    assert_eq!(points(colony.into_iter().map(|(coord, x)| (coord, x.is_alive())).collect()), expected);
+    assert_eq!(points(hashlife.into_iter().map(|(coord, x)| (coord, x.is_alive())).collect()), expected);
+}
@@ -2,12 +2,18 @@ use sdl2::EventPump;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
-use sdl2::rect::Rect;
+use sdl2::rect::{ Point, Rect };
 use sdl2::render::Renderer;
 use sdl2;
 use std::{ thread, time };
 
-use cell::Cell;
+use automaton::Automaton;
+use spacetime::SpaceTime;
+use tiling::{ Hexagons, Triangles };
+use turmite::Turmites;
+use volume::Volume;
+use cell::{ Cell, State };
+use coord::{ Hex, Tri };
 use coord::Dim2 as Coord;
 use grid::Grid;
 use world::World;
@@ -20,13 +26,161 @@ pub struct Settings {
 }
 
 
-pub fn run<T>(mut world: World<T>, settings: Settings)
-    where T: Grid + IntoIterator<Item = (Coord, Cell)> {
+pub fn run<T>(world: World<T>, settings: Settings)
+    where T: Grid<Cell = Cell> + IntoIterator<Item = (Coord, Cell)> {
+    run_with(world, settings, |r, cell_size, grid| render(r, cell_size, grid, &cell_color));
+}
+
+/// Runs a rule table automaton, drawing states with the colors of its table.
+pub fn run_automaton(world: World<Automaton>, settings: Settings) {
+    let colors = world.grid().table().colors();
+
+    let color = move |x: State| {
+        let (r, g, b) = colors[x.0 as usize];
+        Color::RGB(r, g, b)
+    };
+
+    run_with(world, settings, |r, cell_size, grid| render(r, cell_size, grid, &color));
+}
+
+/// Runs turmites, drawing colors in shades of grey and ants in red.
+pub fn run_turmites(world: World<Turmites>, settings: Settings) {
+    let n = world.grid().turmite().colors();
+    let color = move |x: State| shade(x, n);
+
+    run_with(world, settings, |r, cell_size, grid: Turmites| {
+        let ants = grid.ants().to_vec();
+        render_cells(r, cell_size, grid, &color);
+
+        for ant in ants {
+            render_cell(r, cell_size, ant.position.into(), Color::RGB(255, 0, 0));
+        }
+
+        r.present();
+    });
+}
+
+/// Runs a one dimensional automaton, each generation a row below the
+/// previous one.
+pub fn run_spacetime(world: World<SpaceTime>, settings: Settings) {
+    let n = world.grid().wolfram().colors();
+    let color = move |x: State| shade(x, n);
+
+    run_with(world, settings, |r, cell_size, grid| render(r, cell_size, grid, &color));
+}
+
+/// Runs a 3D grid one slice at a time, Up and Down scrolling through them.
+pub fn run_volume(mut world: World<Volume>, settings: Settings) {
+    let cell_size = settings.cell_size;
+    let (rows, cols) = world.size();
+    let depth = world.grid().depth();
+
+    let (mut r, mut e) = init((cols * cell_size) as u32, (rows * cell_size) as u32);
+
+    let mut running = false;
+    let mut grid = world.grid().clone();
+    let mut z = 0;
+    let mut redraw = true;
+
+    'running:loop {
+        for event in e.poll_iter() {
+            match event {
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    break 'running
+                }
+                Event::KeyDown { keycode: Some(Keycode::Space), .. } => {
+                    running = !running;
+                }
+                Event::KeyDown { keycode: Some(Keycode::Up), .. } if depth > 0 => {
+                    z = (z + 1) % depth;
+                    redraw = true;
+                }
+                Event::KeyDown { keycode: Some(Keycode::Down), .. } if depth > 0 => {
+                    z = (z + depth - 1) % depth;
+                    redraw = true;
+                }
+                _ =>  {}
+            }
+        }
+
+        if running {
+            if let Some(next) = world.next() {
+                grid = next;
+                redraw = true;
+            }
+
+            thread::sleep(time::Duration::from_millis(settings.delay as u64));
+        }
+
+        // A grid without depth has no slice to draw.
+        if redraw && depth > 0 {
+            render_slice(&mut r, cell_size, &grid, z);
+        }
+
+        redraw = false;
+    }
+}
+
+/// Runs a hexagonal grid, `cell_size` being the width of a hexagon.
+pub fn run_hexagons(world: World<Hexagons>, settings: Settings) {
+    let w = settings.cell_size as f64;
+    let h = w * 2.0 / 3f64.sqrt();
+    let (rows, cols) = world.size();
+    let last = rows.saturating_sub(1);
+    let window = (w * (cols as f64 + last as f64 / 2.0), 0.75 * h * last as f64 + h);
+
+    run_in(world, settings, window, move |r, _, grid| {
+        render_polygons(r, grid, |coord: Hex| {
+            let cx = w * (coord.q() as f64 + (last - coord.r()) as f64 / 2.0 + 0.5);
+            let cy = 0.75 * h * coord.r() as f64 + h / 2.0;
+
+            (0..6)
+                .map(|i| (60.0 * i as f64 - 90.0).to_radians())
+                .map(|x| (cx + h / 2.0 * x.cos(), cy + h / 2.0 * x.sin()))
+                .collect()
+        });
+    });
+}
+
+/// Runs a triangular grid, `cell_size` being the side of a triangle.
+pub fn run_triangles(world: World<Triangles>, settings: Settings) {
+    let s = settings.cell_size as f64;
+    let t = s * 3f64.sqrt() / 2.0;
+    let (rows, cols) = world.size();
+    let window = (s * (cols as f64 + 1.0) / 2.0, t * rows as f64);
+
+    run_in(world, settings, window, move |r, _, grid| {
+        render_polygons(r, grid, |coord: Tri| {
+            let (x, y) = (s * coord.y() as f64 / 2.0, t * coord.x() as f64);
+
+            if coord.points_up() {
+                vec![(x, y + t), (x + s, y + t), (x + s / 2.0, y)]
+            } else {
+                vec![(x, y), (x + s, y), (x + s / 2.0, y + t)]
+            }
+        });
+    });
+}
+
+
+fn run_with<T, F>(world: World<T>, settings: Settings, render: F)
+    where T: Grid + IntoIterator<Item = (Coord, <T as Grid>::Cell)>,
+          F: Fn(&mut Renderer, usize, T) {
+    let (rows, cols) = world.size();
+    let window = ((cols * settings.cell_size) as f64, (rows * settings.cell_size) as f64);
+
+    run_in(world, settings, window, render);
+}
+
+/// Runs in a window of the given width and height.
+fn run_in<T, F>(mut world: World<T>, settings: Settings, window: (f64, f64), render: F)
+    where T: Grid,
+          World<T>: Iterator<Item = T>,
+          F: Fn(&mut Renderer, usize, T) {
     let delay = settings.delay;
     let cell_size = settings.cell_size;
-    let (width, _) = world.size();
 
-    let (mut r, mut e) = init((width * cell_size) as u32);
+    let (mut r, mut e) = init(window.0.ceil() as u32, window.1.ceil() as u32);
 
     let mut running = false;
 
@@ -54,8 +208,17 @@ pub fn run<T>(mut world: World<T>, settings: Settings)
 }
 
 
-fn render<G>(r: &mut Renderer, cell_size: usize, grid: G)
-    where G: Grid + IntoIterator<Item = (Coord, Cell)> {
+fn render<G, C, F>(r: &mut Renderer, cell_size: usize, grid: G, color: &F)
+    where G: Grid + IntoIterator<Item = (Coord, C)>,
+          F: Fn(C) -> Color {
+    render_cells(r, cell_size, grid, color);
+    r.present();
+}
+
+/// Draws every cell without presenting, so more can be drawn on top.
+fn render_cells<G, C, F>(r: &mut Renderer, cell_size: usize, grid: G, color: &F)
+    where G: Grid + IntoIterator<Item = (Coord, C)>,
+          F: Fn(C) -> Color {
 
     println!("{}\n\n", grid);
 
@@ -63,18 +226,94 @@ fn render<G>(r: &mut Renderer, cell_size: usize, grid: G)
     r.clear();
 
     for (coord, cell) in grid {
-        render_cell(r, cell_size, coord, cell)
+        render_cell(r, cell_size, coord, color(cell))
+    }
+}
+
+/// Draws every cell as the polygon with the given corners.
+fn render_polygons<G, C, F>(r: &mut Renderer, grid: G, corners: F)
+    where G: Grid + IntoIterator<Item = (C, Cell)>,
+          F: Fn(C) -> Vec<(f64, f64)> {
+
+    println!("{}\n\n", grid);
+
+    r.set_draw_color(Color::RGB(250, 250, 250));
+    r.clear();
+
+    for (coord, cell) in grid {
+        render_polygon(r, &corners(coord), cell_color(cell));
+    }
+
+    r.present();
+}
+
+/// Fills a convex polygon row by row, then outlines it.
+#[allow(unused_must_use)]
+fn render_polygon(r: &mut Renderer, corners: &[(f64, f64)], color: Color) {
+    let n = corners.len();
+    let ys = corners.iter().map(|&(_, y)| y);
+    let top = ys.clone().fold(::std::f64::INFINITY, f64::min).ceil() as i32;
+    let bottom = ys.fold(::std::f64::NEG_INFINITY, f64::max).floor() as i32;
+
+    r.set_draw_color(color);
+
+    for y in top..bottom + 1 {
+        let y = y as f64;
+        let xs: Vec<f64> = (0..n)
+            .map(|i| (corners[i], corners[(i + 1) % n]))
+            .filter(|&((_, y0), (_, y1))| y0 != y1 && y0.min(y1) <= y && y <= y0.max(y1))
+            .map(|((x0, y0), (x1, y1))| x0 + (y - y0) * (x1 - x0) / (y1 - y0))
+            .collect();
+
+        if xs.is_empty() {
+            continue;
+        }
+
+        let left = xs.iter().cloned().fold(::std::f64::INFINITY, f64::min).round() as i32;
+        let right = xs.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max).round() as i32;
+
+        r.draw_line(Point::new(left, y as i32), Point::new(right, y as i32));
+    }
+
+    let mut outline: Vec<Point> = corners.iter()
+        .map(|&(x, y)| Point::new(x.round() as i32, y.round() as i32))
+        .collect();
+    outline.push(outline[0]);
+
+    r.set_draw_color(Color::RGB(200, 200, 200));
+    r.draw_lines(&outline[..]);
+}
+
+fn render_slice(r: &mut Renderer, cell_size: usize, grid: &Volume, z: usize) {
+    let slice = grid.slice(z);
+    let text: Vec<String> = slice.outer_iter()
+        .map(|xs| xs.iter().map(|x| format!("{}", x)).collect())
+        .collect();
+
+    println!("z = {}\n{}\n\n", z, text.join("\n"));
+
+    r.set_draw_color(Color::RGB(250, 250, 250));
+    r.clear();
+
+    for ((x, y), &cell) in slice.indexed_iter() {
+        render_cell(r, cell_size, Coord(x, y), cell_color(cell))
     }
 
     r.present();
 }
 
 #[allow(unused_must_use)]
-fn render_cell(r: &mut Renderer, cell_size: usize, coord: Coord, cell: Cell) {
-    let x = cell_size * coord.x();
-    let y = cell_size * coord.y();
+fn render_cell(r: &mut Renderer, cell_size: usize, coord: Coord, color: Color) {
+    // Coordinates are (row, column).
+    let x = cell_size * coord.y();
+    let y = cell_size * coord.x();
+
+    r.set_draw_color(color);
+    r.fill_rect(Rect::new(x as i32, y as i32, cell_size as u32, cell_size as u32));
+}
 
-    let cell_color = match cell {
+fn cell_color(cell: Cell) -> Color {
+    match cell {
         Cell::Alive => Color::RGB(0, 255, 0),
         Cell::Unborn => Color::RGB(255, 255, 255),
         Cell::Dead(x) => match x {
@@ -84,18 +323,21 @@ fn render_cell(r: &mut Renderer, cell_size: usize, coord: Coord, cell: Cell) {
             }
             _ => Color::RGB(0, 0, 0),
         }
-    };
+    }
+}
 
-    r.set_draw_color(cell_color);
-    r.fill_rect(Rect::new(x as i32, y as i32, cell_size as u32, cell_size as u32));
+/// White for the empty state to black for the last of `n` ones.
+fn shade(x: State, n: usize) -> Color {
+    let y = 255 - (x.0 as usize * 255 / (n - 1)) as u8;
+    Color::RGB(y, y, y)
 }
 
 
-fn init<'a>(size: u32)-> (Renderer<'a>, EventPump) {
+fn init<'a>(width: u32, height: u32)-> (Renderer<'a>, EventPump) {
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
 
-    let window = video_subsystem.window("Game of Life", size , size)
+    let window = video_subsystem.window("Game of Life", width, height)
                                 .position_centered()
                                 .opengl()
                                 .build()
@@ -0,0 +1,372 @@
+//! Soup search.
+//!
+//! Random 16 x 16 soups are run in the middle of a larger bounded plane until
+//! the world repeats itself, then every object left is identified by its
+//! apgcode. Spaceships escaping the soup are taken out and counted before
+//! they reach the edges, where they would turn into debris. Soups are
+//! numbered and derived from a seed string, so any soup in a report can be
+//! generated again from the seed and its number.
+
+use std::collections::{ BTreeMap, HashMap, HashSet };
+use std::fmt;
+use std::fs::File;
+use std::io::{ self, Write };
+use std::path::Path;
+use std::thread;
+use ndarray::prelude::*;
+use num_cpus;
+
+use analysis::Shape;
+use apgcode;
+use cell::Cell;
+use census::{ self, Catalog, Kind };
+use colony::Colony;
+use community::Community;
+use coord::Point;
+use format::rle;
+use grid::Grid;
+use pattern::{ Custom, Layout, Noise, Random };
+use topology::Topology;
+use world::World;
+
+
+/// Soup side length.
+pub const SOUP: usize = 16;
+
+/// Side length of the bounded plane soups are run on, the soup in the
+/// middle.
+pub const ARENA: usize = 96;
+
+/// Width of the band along the edges of the arena where spaceships are taken
+/// out.
+const MARGIN: usize = 16;
+
+/// Generations between two looks for spaceships in the margin. Even at c/2
+/// they cross only half of it meanwhile.
+const ROUND: usize = 16;
+
+/// Generations after which a soup is censused even if it didn't settle.
+const MAX_GENERATIONS: usize = 10000;
+
+/// Prefix of the codes of objects that don't repeat themselves on their own.
+/// It is local to this crate: Catagolue has no code for them, and uses `zz_`
+/// for pathological soups instead.
+const UNCLASSIFIED: &'static str = "un_";
+
+/// Soups kept for each object in the report.
+const SAMPLES: usize = 3;
+
+/// Objects found at most this many times are listed with their soups.
+const RARE: usize = 3;
+
+
+/// The soup with the given number, the same for a given seed on every
+/// platform.
+pub fn soup(seed: &str, index: usize) -> Community {
+    let mut grid = Community::empty(SOUP);
+    grid.insert(Layout::new((0, 0), cells(seed, index)));
+
+    grid
+}
+
+/// The cells of the soup with the given number.
+fn cells(seed: &str, index: usize) -> Noise {
+    Random(SOUP, SOUP)
+        .with_seed(fnv(format!("{}{}", seed, index).as_bytes()))
+        .with_density(0.5)
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    seed: String,
+    soups: usize,
+    threads: usize,
+}
+
+impl Search {
+    /// Searches the first `soups` soups of the seed, using every core.
+    pub fn new(seed: &str, soups: usize) -> Self {
+        Search {
+            seed: seed.to_string(),
+            soups: soups,
+            threads: num_cpus::get(),
+        }
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = if threads == 0 { 1 } else { threads };
+        self
+    }
+
+    pub fn run(&self) -> Report {
+        let handles: Vec<thread::JoinHandle<Report>> = (0..self.threads)
+            .map(|first| {
+                let seed = self.seed.clone();
+                let (soups, step) = (self.soups, self.threads);
+
+                thread::spawn(move || {
+                    let mut report = Report::new(&seed);
+                    let mut codes = HashMap::new();
+                    let period = Catalog::default().period();
+
+                    for index in (first..soups).step_by(step) {
+                        report.add(index, &search(&seed, index, period, &mut codes));
+                    }
+
+                    report
+                })
+            })
+            .collect();
+
+        let mut report = Report::new(&self.seed);
+
+        for handle in handles {
+            report.merge(handle.join().expect("Search thread panicked"));
+        }
+
+        report
+    }
+}
+
+
+/// Objects found, by apgcode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub seed: String,
+    pub soups: usize,
+    pub counts: BTreeMap<String, usize>,
+    /// The first soups each object was found in.
+    pub samples: BTreeMap<String, Vec<usize>>,
+}
+
+impl Report {
+    fn new(seed: &str) -> Self {
+        Report {
+            seed: seed.to_string(),
+            .. Report::default()
+        }
+    }
+
+    fn add(&mut self, index: usize, codes: &[String]) {
+        self.soups += 1;
+
+        for code in codes {
+            *self.counts.entry(code.clone()).or_insert(0) += 1;
+
+            let samples = self.samples.entry(code.clone()).or_insert_with(Vec::new);
+
+            if samples.last() != Some(&index) && samples.len() < SAMPLES {
+                samples.push(index);
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Report) {
+        self.soups += other.soups;
+
+        for (code, n) in other.counts {
+            *self.counts.entry(code).or_insert(0) += n;
+        }
+
+        for (code, xs) in other.samples {
+            let samples = self.samples.entry(code).or_insert_with(Vec::new);
+            samples.extend(xs);
+            samples.sort();
+            samples.truncate(SAMPLES);
+        }
+    }
+
+    /// Writes the report as text, see `Display`.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(self.to_string().as_bytes())
+    }
+}
+
+/// Counts from the most common object, followed by the soups of the rare
+/// and unclassified ones as RLE.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "seed = {}, soups = {}", self.seed, self.soups)?;
+        writeln!(f)?;
+
+        let mut counts: Vec<(&String, &usize)> = self.counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+        for &(code, n) in &counts {
+            writeln!(f, "{} {}", code, n)?;
+        }
+
+        let listed = counts.iter()
+            .filter(|&&(code, &n)| n <= RARE || code.starts_with(UNCLASSIFIED));
+
+        for &(code, &n) in listed {
+            for &index in self.samples.get(code).into_iter().flat_map(|x| x) {
+                writeln!(f)?;
+                writeln!(f, "#C {} ({} found) in soup {}", code, n, index)?;
+                write!(f, "{}", rle::write(soup(&self.seed, index)))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// The apgcodes of the objects the soup settles into, each followed over
+/// `period` generations, and of the spaceships escaping it. Codes are
+/// memoized by canonical shape.
+fn search(seed: &str, index: usize, period: usize, codes: &mut HashMap<Vec<Point>, String>)
+          -> Vec<String> {
+    let offset = (ARENA - SOUP) / 2;
+    let mut grid = Community::empty(ARENA).with_topology(Topology::Plane);
+    grid.insert(Layout::new((offset, offset), cells(seed, index)));
+
+    let (grid, escaped) = settle(grid);
+    let objects = census::objects_over(grid, census::DISTANCE, period - 1);
+
+    escaped.into_iter()
+        .chain(objects)
+        .map(|shape| {
+            let key = shape.canonical().cells().to_vec();
+
+            codes.entry(key)
+                .or_insert_with(|| {
+                    let object = Colony::new(shape.cells().iter().cloned(), 1);
+                    apgcode::encode(&object).unwrap_or_else(|| unclassified(&shape))
+                })
+                .clone()
+        })
+        .collect()
+}
+
+/// Runs the grid until it repeats itself, or for `MAX_GENERATIONS`, taking
+/// out the spaceships reaching the margin. The world starts over without
+/// them, so they never hit the edges.
+fn settle(grid: Community) -> (Community, Vec<Shape>) {
+    let mut world = World::new(grid).with_cycle_detection(true);
+    let mut escaped = vec![];
+    let mut gen = 0;
+
+    while gen < MAX_GENERATIONS && world.stabilization().is_none() {
+        gen += world.by_ref().take(ROUND).count();
+
+        let spaceships = escaping(world.grid());
+
+        if spaceships.is_empty() {
+            continue;
+        }
+
+        let mut grid = world.grid().clone();
+        let gone: HashSet<Point> = spaceships.iter().flat_map(|x| absolute(x)).collect();
+
+        for point in gone {
+            let dead = Custom(arr2(&[[Cell::Unborn]]));
+            grid.insert(Layout::new((point.0 as usize, point.1 as usize), dead));
+        }
+
+        escaped.extend(spaceships);
+        world = World::new(grid).with_cycle_detection(true);
+    }
+
+    (world.grid().clone(), escaped)
+}
+
+/// The spaceships with cells in the margin of the arena.
+fn escaping(grid: &Community) -> Vec<Shape> {
+    let (lower, upper) = (MARGIN as isize, (ARENA - MARGIN) as isize);
+    let outside = |x: isize| x < lower || x >= upper;
+
+    census::objects(grid.clone(), census::DISTANCE)
+        .into_iter()
+        .filter(|x| absolute(x).iter().any(|p| outside(p.0) || outside(p.1)))
+        .filter(|x| Kind::of(x, grid.rule()) == Kind::Spaceship)
+        .collect()
+}
+
+/// The cells of the shape where it was found.
+fn absolute(shape: &Shape) -> Vec<Point> {
+    let origin = shape.origin();
+
+    shape.cells().iter().map(|x| x.offset(origin.0, origin.1)).collect()
+}
+
+/// The code of an object that doesn't repeat itself on its own: its cells in
+/// the smallest orientation.
+fn unclassified(shape: &Shape) -> String {
+    let code = shape.orientations()
+        .iter()
+        .map(apgcode::wechsler)
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default();
+
+    format!("{}{}", UNCLASSIFIED, code)
+}
+
+/// FNV-1a hash, stable across platforms and releases.
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &x| {
+        (hash ^ x as u64).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+
+#[test]
+fn test_soup() {
+    assert_eq!(fnv(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(soup("k_test", 7), soup("k_test", 7));
+    assert!(soup("k_test", 7) != soup("k_test", 8));
+    assert!(soup("k_test", 7) != soup("k_tesT", 7));
+}
+
+#[test]
+fn test_search() {
+    let one = Search::new("k_test", 2).with_threads(1).run();
+    let many = Search::new("k_test", 2).with_threads(2).run();
+
+    assert_eq!(one, many);
+    assert_eq!(one.soups, 2);
+    assert!(one.counts.get("xs4_33").map_or(false, |&x| x > 0));
+    assert!(one.samples.values().all(|xs| !xs.is_empty() && xs.len() <= SAMPLES));
+
+    let path = ::std::env::temp_dir().join("conway_soup_report.txt");
+    one.write(&path).unwrap();
+
+    let mut s = String::new();
+    io::Read::read_to_string(&mut File::open(&path).unwrap(), &mut s).unwrap();
+
+    assert!(s.starts_with("seed = k_test, soups = 2\n\n"));
+    assert!(s.contains("\nxs4_33 "));
+}
+
+#[test]
+fn test_escaping() {
+    use pattern::{ Block, Glider };
+
+    let mut grid = Community::empty(ARENA).with_topology(Topology::Plane);
+    grid.insert(Layout::new((40, 40), Block));
+    grid.insert(Layout::new((50, 50), Glider::BottomRight));
+
+    // The glider is taken out before it reaches the edge and turns into a
+    // block there.
+    let (grid, escaped) = settle(grid);
+
+    assert_eq!(escaped.len(), 1);
+    assert_eq!(apgcode::encode(&Colony::new(escaped[0].cells().iter().cloned(), 1)),
+               Some("xq4_153".to_string()));
+    assert_eq!(census::objects(grid, census::DISTANCE).len(), 1);
+}
+
+#[test]
+fn test_unclassified() {
+    let shape = Shape::new(vec![Point(0, 0), Point(0, 1), Point(1, 0)]).unwrap();
+    assert_eq!(unclassified(&shape), "un_13");
+
+    // Listed with their soups however often they're found.
+    let mut report = Report::new("k_test");
+    report.add(2, &vec![unclassified(&shape); RARE + 1]);
+
+    assert!(report.to_string().contains("\n#C un_13 (4 found) in soup 2\n"));
+}
+
@@ -0,0 +1,361 @@
+//! One dimensional automata, drawn as space-time diagrams: each generation
+//! is a row below the previous one.
+//!
+//! Rules look at a cell and its 2 neighbours:
+//!
+//! * Wolfram's elementary rules `W0` to `W255`, the bits of the number being
+//!   the next state of each of the 8 neighbourhoods, `111` first.
+//! * Totalistic rules with `k` colors, e.g. `K3,C777`: digit `i` in base `k`
+//!   of the code is the next state when the 3 cells add up to `i`.
+//!
+//! Codes are 64 bit numbers, which only hold every code of up to 7 colors:
+//! totalistic rules with 8 colors or more are rejected.
+
+use itertools::Itertools;
+use ndarray::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+use cell::{ Cell, State };
+use community::Community;
+use coord::Dim2 as Coord;
+use grid::Grid;
+use topology::Topology;
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Wolfram {
+    Elementary(u8),
+    Totalistic { colors: usize, code: u64 },
+}
+
+impl Wolfram {
+    /// Number of states, the empty one included.
+    pub fn colors(&self) -> usize {
+        match *self {
+            Wolfram::Elementary(_) => 2,
+            Wolfram::Totalistic { colors, .. } => colors,
+        }
+    }
+
+    /// The next state of the middle cell.
+    pub fn fate(&self, left: State, cell: State, right: State) -> State {
+        match *self {
+            Wolfram::Elementary(code) => {
+                let i = (left.0.min(1) << 2) | (cell.0.min(1) << 1) | right.0.min(1);
+                State((code >> i) & 1)
+            }
+            Wolfram::Totalistic { colors, code } => {
+                // States beyond the rule count as its last one.
+                let max = (colors - 1) as u8;
+                let sum = [left, cell, right].iter().map(|x| x.0.min(max) as u32).sum::<u32>();
+                State((code / (colors as u64).pow(sum) % colors as u64) as u8)
+            }
+        }
+    }
+}
+
+impl FromStr for Wolfram {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Wolfram, String> {
+        let invalid = || format!("Invalid rulestring {:?}", s);
+        let s = s.trim().to_uppercase();
+        let number = |part: &str, prefix: char| -> Result<u64, String> {
+            if !part.starts_with(prefix) {
+                return Err(invalid());
+            }
+
+            part[1..].trim().parse().map_err(|_| invalid())
+        };
+
+        if s.starts_with('W') {
+            return match number(&s, 'W')? {
+                x if x < 256 => Ok(Wolfram::Elementary(x as u8)),
+                _ => Err(invalid()),
+            };
+        }
+
+        let parts: Vec<&str> = s.split(',').map(|x| x.trim()).collect();
+
+        if parts.len() != 2 {
+            return Err(invalid());
+        }
+
+        let (colors, code) = (number(parts[0], 'K')? as usize, number(parts[1], 'C')?);
+
+        // The sums of 3 cells go from 0 to 3(k - 1), one digit each, so the
+        // largest code must fit in a `u64`.
+        let valid = colors >= 2 && (colors as u64)
+            .checked_pow(3 * (colors as u32 - 1) + 1)
+            .map_or(false, |max| code < max);
+
+        if !valid {
+            return Err(invalid());
+        }
+
+        Ok(Wolfram::Totalistic { colors: colors, code: code })
+    }
+}
+
+impl fmt::Display for Wolfram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Wolfram::Elementary(code) => write!(f, "W{}", code),
+            Wolfram::Totalistic { colors, code } => write!(f, "K{},C{}", colors, code),
+        }
+    }
+}
+
+
+/// A line of cells and its past generations, one per row. Once every row is
+/// filled the diagram scrolls up, the oldest generation being dropped.
+#[derive(Debug, Clone, Eq)]
+pub struct SpaceTime {
+    cells: Array2<State>,
+    size: (usize, usize),
+    gen: usize,
+    /// The row of the current generation.
+    row: usize,
+    rule: Wolfram,
+    topology: Topology,
+}
+
+impl SpaceTime {
+    /// `rows` generations of an empty line of `cols` cells.
+    pub fn new(rule: Wolfram, rows: usize, cols: usize) -> Self {
+        SpaceTime {
+            cells: Array2::from_elem((rows, cols), State(0)),
+            size: (rows, cols),
+            gen: 1,
+            row: 0,
+            rule: rule,
+            topology: Topology::default(),
+        }
+    }
+
+    /// A single cell in the middle of the line.
+    pub fn single(rule: Wolfram, rows: usize, cols: usize) -> Self {
+        let mut grid = SpaceTime::new(rule, rows, cols);
+        grid.cells[[0, cols / 2]] = State(1);
+
+        grid
+    }
+
+    /// Joins the ends of the line unless given a topology without joined
+    /// columns.
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn wolfram(&self) -> Wolfram {
+        self.rule
+    }
+
+    pub fn cells(&self) -> &Array2<State> {
+        &self.cells
+    }
+
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+
+    /// The current generation.
+    pub fn line(&self) -> Vec<State> {
+        self.cells.row(self.row).iter().cloned().collect()
+    }
+
+    /// Replaces the current generation, e.g. with random cells.
+    ///
+    /// Panics if the line doesn't fit the grid or has states beyond the
+    /// colors of the rule.
+    pub fn set_line(&mut self, line: &[State]) {
+        if line.len() != self.size.1 {
+            panic!("Line of {} cells in grid of {:?}", line.len(), self.size);
+        }
+
+        if let Some(x) = line.iter().find(|x| x.0 as usize >= self.rule.colors()) {
+            panic!("State {} out of range for the rule {}", x.0, self.rule);
+        }
+
+        self.cells.row_mut(self.row).assign(&aview1(line));
+    }
+}
+
+
+impl Grid for SpaceTime {
+    type Cell = State;
+    type Coord = Coord;
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn item(&self, coord: Coord) -> State {
+        let (x, y) = coord.into();
+        self.cells[[x, y]]
+    }
+
+    /// The cells left and right of it in the same generation.
+    fn item_neighbours(&self, coord: Coord) -> Vec<State> {
+        let (x, y) = coord.into();
+
+        self.topology.neighbours((0, y), &[(0, -1), (0, 1)], (1, self.size.1))
+            .into_iter()
+            .map(|z| z.map_or(State(0), |(_, z)| self.cells[[x, z]]))
+            .collect()
+    }
+
+    /// The state of the cell below it in the next generation.
+    fn item_fate(&self, coord: Coord) -> State {
+        let xs = self.item_neighbours(coord);
+        self.rule.fate(xs[0], self.item(coord), xs[1])
+    }
+
+    fn evolve(&self) -> Self {
+        let line: Vec<State> = (0..self.size.1)
+            .map(|y| self.item_fate(Coord(self.row, y)))
+            .collect();
+
+        let mut grid = self.clone();
+        grid.gen += 1;
+
+        if self.row + 1 < self.size.0 {
+            grid.row += 1;
+        } else {
+            let n = self.size.0 as isize;
+            grid.cells.slice_mut(s![..n - 1, ..]).assign(&self.cells.slice(s![1.., ..]));
+        }
+
+        grid.set_line(&line);
+        grid
+    }
+}
+
+
+impl IntoIterator for SpaceTime {
+    type Item = (Coord, State);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.iter()
+            .enumerate()
+            .map(|(i, &x)| (Coord::from_row_major(i, self.size.1), x))
+            .collect::<Vec<(Coord, State)>>()
+            .into_iter()
+    }
+}
+
+
+impl PartialEq for SpaceTime {
+    fn eq(&self, other: &SpaceTime) -> bool {
+        self.cells == other.cells
+    }
+}
+
+
+impl fmt::Display for SpaceTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let res = self.cells.iter()
+            .map(|x| format!("{}", x))
+            .chunks(self.size.1)
+            .into_iter()
+            .map(|xs| format!("{}\n", xs.collect::<String>()))
+            .collect::<String>();
+
+        write!(f, "{}", res)
+    }
+}
+
+
+/// The diagram as a 2D grid, any state but the empty one being alive.
+impl From<SpaceTime> for Community {
+    fn from(x: SpaceTime) -> Community {
+        let cells = Array2::from_shape_fn(x.size, |(i, j)| {
+            if x.cells[[i, j]].0 == 0 { Cell::Unborn } else { Cell::Alive }
+        });
+
+        Community::new(cells, 1)
+    }
+}
+
+
+#[test]
+fn test_parse() {
+    assert_eq!("W30".parse(), Ok(Wolfram::Elementary(30)));
+    assert_eq!("w110".parse::<Wolfram>().map(|x| x.to_string()), Ok("W110".to_string()));
+    assert_eq!("K3,C777".parse(), Ok(Wolfram::Totalistic { colors: 3, code: 777 }));
+    assert_eq!(Wolfram::Totalistic { colors: 3, code: 777 }.to_string(), "K3,C777");
+    assert_eq!("k3, c2186".parse::<Wolfram>().map(|x| x.colors()), Ok(3));
+
+    let xs = vec!["", "30", "W256", "Wx", "K3", "K3,C2187", "K1,C0", "C10,K2", "K8,C0", "K9,C0"];
+
+    for s in xs {
+        assert!(s.parse::<Wolfram>().is_err(), "{:?} should not parse", s);
+    }
+}
+
+#[test]
+fn test_elementary() {
+    let mut grid = SpaceTime::single("W30".parse().unwrap(), 5, 9);
+
+    for _ in 0..4 {
+        grid = grid.evolve();
+    }
+
+    assert_eq!(format!("{}", grid), "\
+....A....
+...AAA...
+..AA..A..
+.AA.AAAA.
+AA..A...A
+");
+
+    // Once full, the diagram scrolls.
+    grid = grid.evolve();
+
+    assert_eq!(grid.generation(), 6);
+    assert_eq!(format!("{}", grid).lines().next(), Some("...AAA..."));
+    assert_eq!(grid.line(), grid.cells().row(4).iter().cloned().collect::<Vec<_>>());
+
+    let community = Community::from(grid);
+    assert_eq!(community.item(Coord(0, 4)), Cell::Alive);
+    assert_eq!(community.item(Coord(0, 0)), Cell::Unborn);
+}
+
+#[test]
+fn test_totalistic() {
+    // Rule 150 is the parity of the 3 cells, a totalistic rule.
+    let a = SpaceTime::single("W150".parse().unwrap(), 16, 31);
+    let b = SpaceTime::single("K2,C10".parse().unwrap(), 16, 31);
+    let (mut a, mut b) = (a.with_topology(Topology::Plane), b.with_topology(Topology::Plane));
+
+    for _ in 0..15 {
+        a = a.evolve();
+        b = b.evolve();
+    }
+
+    assert_eq!(a, b);
+
+    // Sums of 1 become 2, of 2 become 1, anything else empties the cell.
+    let mut grid = SpaceTime::single("K3,C15".parse().unwrap(), 3, 5);
+    grid = grid.evolve();
+
+    assert_eq!(grid.line(), vec![State(0), State(2), State(2), State(2), State(0)]);
+    assert_eq!(grid.item_fate(Coord(1, 1)), State(0));
+    assert_eq!(grid.item_fate(Coord(1, 0)), State(1));
+
+    // States beyond the colors count as the last one, 2 + 2 + 0 here.
+    let rule: Wolfram = "K3,C15".parse().unwrap();
+    assert_eq!(rule.fate(State(200), State(2), State(0)), State(0));
+    assert_eq!(rule.fate(State(200), State(0), State(0)), State(1));
+    assert_eq!(rule.fate(State(0), State(200), State(0)), rule.fate(State(0), State(2), State(0)));
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn test_set_line_out_of_range() {
+    let mut grid = SpaceTime::new("K3,C15".parse().unwrap(), 3, 3);
+    grid.set_line(&[State(0), State(3), State(0)]);
+}
@@ -0,0 +1,585 @@
+//! Rule tables, as used by Golly for automata with any number of states.
+//!
+//! A `.rule` file has a `@RULE` line with its name, a `@TABLE` section with
+//! the transitions and an optional `@COLORS` section:
+//!
+//! ```text
+//! @RULE WireWorld
+//!
+//! @TABLE
+//! n_states:4
+//! neighborhood:Moore
+//! symmetries:permute
+//! var a={0,1,2,3}
+//! var b={0,1,2,3}
+//! ...
+//! # cell, neighbours clockwise from the top, next state
+//! 1,a,b,c,d,e,f,g,h,2
+//! 3,1,2,a,b,c,d,e,f,1
+//!
+//! @COLORS
+//! 1 0 128 255
+//! ```
+//!
+//! The first transition matching a cell and its neighbours gives its next
+//! state, cells matching none stay as they are. A variable stands for any of
+//! its states, the same one wherever it appears in a transition. Symmetries
+//! add every rotation, reflection or permutation of the neighbours of each
+//! transition.
+
+use std::collections::{ HashMap, HashSet };
+
+use cell::State;
+
+
+/// Neighbourhoods of rule tables, with neighbours listed clockwise from the
+/// top.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Neighbourhood {
+    Moore,
+    VonNeumann,
+    /// The 6 neighbours of a hexagonal grid drawn on a square one, without
+    /// the top right and bottom left corners.
+    Hexagonal,
+}
+
+impl Neighbourhood {
+    /// Offsets of the neighbours, as (rows, columns), in the order of the
+    /// transitions.
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        match *self {
+            Neighbourhood::Moore => {
+                &[(-1, 0), (-1, 1), (0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1)]
+            }
+            Neighbourhood::VonNeumann => &[(-1, 0), (0, 1), (1, 0), (0, -1)],
+            Neighbourhood::Hexagonal => &[(-1, 0), (0, 1), (1, 1), (1, 0), (0, -1), (-1, -1)],
+        }
+    }
+
+    /// The orderings of the neighbours the symmetry stands for.
+    fn symmetries(&self, name: &str) -> Option<Symmetry> {
+        let n = self.offsets().len();
+        let rotate = |step: usize| -> Vec<Vec<usize>> {
+            (0..n / step).map(|k| (0..n).map(|i| (i + k * step) % n).collect()).collect()
+        };
+        let reflect = |xs: Vec<Vec<usize>>| -> Vec<Vec<usize>> {
+            xs.into_iter()
+                .flat_map(|x| {
+                    let mirrored = (0..n).map(|i| x[(n - i) % n]).collect();
+                    vec![x, mirrored]
+                })
+                .collect()
+        };
+
+        let res = match (*self, name) {
+            (_, "permute") => return Some(Symmetry::Permute),
+            (_, "none") => rotate(n),
+            (Neighbourhood::Hexagonal, "reflect") |
+            (Neighbourhood::Moore, "reflect_horizontal") |
+            (Neighbourhood::VonNeumann, "reflect_horizontal") => reflect(rotate(n)),
+            (Neighbourhood::Moore, "rotate4") => rotate(2),
+            (Neighbourhood::Moore, "rotate4reflect") => reflect(rotate(2)),
+            (Neighbourhood::Moore, "rotate8") => rotate(1),
+            (Neighbourhood::Moore, "rotate8reflect") => reflect(rotate(1)),
+            (Neighbourhood::VonNeumann, "rotate4") => rotate(1),
+            (Neighbourhood::VonNeumann, "rotate4reflect") => reflect(rotate(1)),
+            (Neighbourhood::Hexagonal, "rotate2") => rotate(3),
+            (Neighbourhood::Hexagonal, "rotate3") => rotate(2),
+            (Neighbourhood::Hexagonal, "rotate6") => rotate(1),
+            (Neighbourhood::Hexagonal, "rotate6reflect") => reflect(rotate(1)),
+            _ => return None,
+        };
+
+        Some(Symmetry::Orderings(res))
+    }
+}
+
+
+/// How the neighbours of each transition are rearranged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Symmetry {
+    /// The given orderings of the neighbours.
+    Orderings(Vec<Vec<usize>>),
+    /// Any ordering, built from the distinct arrangements of each
+    /// transition's neighbours.
+    Permute,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleTable {
+    name: String,
+    states: usize,
+    neighbourhood: Neighbourhood,
+    transitions: Vec<Transition>,
+    colors: HashMap<u8, (u8, u8, u8)>,
+}
+
+impl RuleTable {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of states, empty included.
+    pub fn states(&self) -> usize {
+        self.states
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /// The next state of a cell, given its neighbours in the order of
+    /// `Neighbourhood::offsets`.
+    pub fn fate(&self, cell: State, neighbours: &[State]) -> State {
+        let mut inputs = Vec::with_capacity(neighbours.len() + 1);
+        inputs.push(cell.0);
+        inputs.extend(neighbours.iter().map(|x| x.0));
+
+        self.transitions.iter()
+            .filter_map(|x| x.apply(&inputs))
+            .next()
+            .map_or(cell, State)
+    }
+
+    /// The color of each state as RGB, from the `@COLORS` section or going
+    /// from red to yellow when missing. Empty cells are dark grey.
+    pub fn colors(&self) -> Vec<(u8, u8, u8)> {
+        (0..self.states)
+            .map(|x| {
+                let default = match x {
+                    0 => (48, 48, 48),
+                    _ if self.states <= 2 => (255, 255, 255),
+                    _ => (255, (255 * (x - 1) / (self.states - 2)) as u8, 0),
+                };
+
+                self.colors.get(&(x as u8)).cloned().unwrap_or(default)
+            })
+            .collect()
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Transition {
+    /// States allowed for the cell, then each neighbour.
+    inputs: Vec<Vec<bool>>,
+    /// Inputs holding the same variable, which must be in the same state.
+    bound: Vec<(usize, usize)>,
+    output: Output,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Output {
+    State(u8),
+    /// The state of the given input.
+    Input(usize),
+}
+
+impl Transition {
+    fn apply(&self, states: &[u8]) -> Option<u8> {
+        let matches = self.inputs.iter()
+            .zip(states)
+            .all(|(allowed, &x)| allowed.get(x as usize).cloned().unwrap_or(false));
+
+        if !matches || self.bound.iter().any(|&(a, b)| states[a] != states[b]) {
+            return None;
+        }
+
+        match self.output {
+            Output::State(x) => Some(x),
+            Output::Input(i) => Some(states[i]),
+        }
+    }
+}
+
+
+/// Reads a `.rule` file, or a bare `.table` without sections.
+pub fn parse(s: &str) -> Result<RuleTable, String> {
+    let mut table = RuleTable {
+        name: String::new(),
+        states: 0,
+        neighbourhood: Neighbourhood::Moore,
+        transitions: vec![],
+        colors: HashMap::new(),
+    };
+
+    let mut section = "@TABLE";
+    let mut name = "none";
+    let mut symmetries: Option<Symmetry> = None;
+    let mut vars: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut found = false;
+
+    for line in s.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('@') {
+            let mut words = line.split_whitespace();
+            section = words.next().unwrap_or("");
+
+            if section == "@RULE" {
+                table.name = words.collect::<Vec<&str>>().join(" ");
+            }
+
+            continue;
+        }
+
+        match section {
+            "@TABLE" => {
+                found = true;
+
+                if let Some(i) = line.find(':') {
+                    let (key, value) = (line[..i].trim(), line[i + 1..].trim());
+
+                    match key {
+                        "n_states" => {
+                            table.states = match value.parse() {
+                                Ok(x) if x >= 2 && x <= 256 => x,
+                                _ => return Err(format!("Invalid number of states {:?}", value)),
+                            };
+                        }
+                        "neighborhood" => {
+                            table.neighbourhood = match value {
+                                "Moore" => Neighbourhood::Moore,
+                                "vonNeumann" => Neighbourhood::VonNeumann,
+                                "hexagonal" => Neighbourhood::Hexagonal,
+                                _ => return Err(format!("Unsupported neighborhood {:?}", value)),
+                            };
+                        }
+                        // Resolved at the first transition, once the neighbourhood
+                        // is known.
+                        "symmetries" => name = value,
+                        _ => return Err(format!("Unknown table setting {:?}", key)),
+                    }
+                } else if line.starts_with("var ") {
+                    let (name, states) = var(&line[4..], &vars, table.states)?;
+                    vars.insert(name, states);
+                } else {
+                    if symmetries.is_none() {
+                        symmetries = Some(table.neighbourhood.symmetries(name)
+                            .ok_or_else(|| format!("Unsupported symmetries {:?}", name))?);
+                    }
+
+                    let xs = transitions(line, &vars, &table, symmetries.as_ref().unwrap())?;
+                    table.transitions.extend(xs);
+                }
+            }
+            "@COLORS" => {
+                let xs: Vec<u8> = line.split_whitespace()
+                    .map(|x| x.parse().map_err(|_| format!("Invalid color {:?}", line)))
+                    .collect::<Result<_, _>>()?;
+
+                if xs.len() != 4 {
+                    return Err(format!("Invalid color {:?}", line));
+                }
+
+                table.colors.insert(xs[0], (xs[1], xs[2], xs[3]));
+            }
+            // Icons, names and anything else aren't used.
+            _ => {}
+        }
+    }
+
+    if !found || table.states == 0 {
+        return Err("Missing @TABLE section with n_states".into());
+    }
+
+    if symmetries.is_none() && table.neighbourhood.symmetries(name).is_none() {
+        return Err(format!("Unsupported symmetries {:?}", name));
+    }
+
+    Ok(table)
+}
+
+
+/// `name={0,1,...}`, other variables standing for their states.
+fn var(s: &str, vars: &HashMap<String, Vec<u8>>, n: usize) -> Result<(String, Vec<u8>), String> {
+    let i = s.find('=').ok_or_else(|| format!("Invalid variable {:?}", s))?;
+    let (name, value) = (s[..i].trim(), s[i + 1..].trim());
+
+    if !value.starts_with('{') || !value.ends_with('}') || name.is_empty() {
+        return Err(format!("Invalid variable {:?}", s));
+    }
+
+    let mut states = vec![];
+
+    for x in value[1..value.len() - 1].split(',').map(|x| x.trim()) {
+        states.extend(token(x, vars, n)?);
+    }
+
+    Ok((name.to_string(), states))
+}
+
+/// The states a token stands for.
+fn token(s: &str, vars: &HashMap<String, Vec<u8>>, n: usize) -> Result<Vec<u8>, String> {
+    match s.parse::<usize>() {
+        Ok(x) if x < n => Ok(vec![x as u8]),
+        Ok(_) => Err(format!("State {} out of range", s)),
+        Err(_) => vars.get(s).cloned().ok_or_else(|| format!("Unknown variable {:?}", s)),
+    }
+}
+
+/// The transition of the line in every ordering of its neighbours.
+fn transitions(line: &str, vars: &HashMap<String, Vec<u8>>, table: &RuleTable,
+               symmetry: &Symmetry) -> Result<Vec<Transition>, String> {
+    let tokens: Vec<String> = if line.contains(',') {
+        line.split(',').map(|x| x.trim().to_string()).collect()
+    } else {
+        line.chars().filter(|c| !c.is_whitespace()).map(|c| c.to_string()).collect()
+    };
+
+    let n = table.neighbourhood.offsets().len();
+
+    if tokens.len() != n + 2 {
+        return Err(format!("Expected {} states in transition {:?}", n + 2, line));
+    }
+
+    // Orderings only differing by where variables used once are, or by
+    // which one, give the same transition.
+    let keys = tokens[..n + 1].iter()
+        .map(|x| {
+            let bound = tokens.iter().filter(|&y| y == x).count() > 1;
+
+            if vars.contains_key(x) && bound {
+                Ok(x.to_string())
+            } else {
+                token(x, vars, table.states).map(|xs| format!("{:?}", xs))
+            }
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+
+    let permuted;
+    let orderings = match *symmetry {
+        Symmetry::Orderings(ref xs) => xs,
+        Symmetry::Permute => {
+            permuted = arrangements(&keys[1..]);
+            &permuted
+        }
+    };
+
+    let mut res = vec![];
+    let mut seen = HashSet::new();
+
+    for ordering in orderings {
+        let mut indices = vec![0];
+        indices.extend(ordering.iter().map(|&i| i + 1));
+
+        if !seen.insert(indices.iter().map(|&i| &keys[i]).collect::<Vec<&String>>()) {
+            continue;
+        }
+
+        let inputs: Vec<&str> = indices.iter().map(|&i| tokens[i].as_str()).collect();
+        let mut allowed = vec![];
+        let mut bound = vec![];
+
+        for (i, &x) in inputs.iter().enumerate() {
+            let mut states = vec![false; table.states];
+
+            for s in token(x, vars, table.states)? {
+                states[s as usize] = true;
+            }
+
+            allowed.push(states);
+
+            if let Some(first) = inputs[..i].iter().position(|&y| y == x && vars.contains_key(x)) {
+                bound.push((first, i));
+            }
+        }
+
+        let last = &tokens[n + 1];
+        let output = match inputs.iter().position(|&x| x == last && vars.contains_key(last)) {
+            Some(i) => Output::Input(i),
+            None => match token(last, &HashMap::new(), table.states) {
+                Ok(xs) => Output::State(xs[0]),
+                Err(_) => return Err(format!("Unbound output {:?} in {:?}", last, line)),
+            },
+        };
+
+        res.push(Transition {
+            inputs: allowed,
+            bound: bound,
+            output: output,
+        });
+    }
+
+    Ok(res)
+}
+
+/// Every distinct ordering of the items, as indices into `keys`. Items with
+/// the same key are interchangeable and keep their relative order.
+fn arrangements(keys: &[String]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+
+    for (i, x) in keys.iter().enumerate() {
+        match groups.iter().position(|g| keys[g[0]] == *x) {
+            Some(j) => groups[j].push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+
+    let mut res = vec![];
+    let mut used = vec![0; groups.len()];
+    arrange(&groups, &mut used, &mut vec![], keys.len(), &mut res);
+
+    res
+}
+
+fn arrange(groups: &[Vec<usize>], used: &mut Vec<usize>, current: &mut Vec<usize>, n: usize,
+           res: &mut Vec<Vec<usize>>) {
+    if current.len() == n {
+        res.push(current.clone());
+        return;
+    }
+
+    for j in 0..groups.len() {
+        if used[j] < groups[j].len() {
+            current.push(groups[j][used[j]]);
+            used[j] += 1;
+            arrange(groups, used, current, n, res);
+            used[j] -= 1;
+            current.pop();
+        }
+    }
+}
+
+
+/// WireWorld, as shipped with Golly.
+pub const WIREWORLD: &'static str = "\
+@RULE WireWorld
+
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,1,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+var n={0,2,3}
+var o={0,2,3}
+# electron head turns into tail
+1,a,b,c,d,e,f,g,h,2
+# electron tail turns into wire
+2,a,b,c,d,e,f,g,h,3
+# wire turns into head with 1 or 2 heads around
+3,1,i,j,k,l,m,n,o,1
+3,1,1,i,j,k,l,m,n,1
+
+@COLORS
+1 0 128 255
+2 255 255 255
+3 255 128 0
+";
+
+
+#[test]
+fn test_parse() {
+    let table = parse(WIREWORLD).unwrap();
+
+    assert_eq!(table.name(), "WireWorld");
+    assert_eq!(table.states(), 4);
+    assert_eq!(table.neighbourhood(), Neighbourhood::Moore);
+    assert_eq!(table.colors(), vec![(48, 48, 48), (0, 128, 255), (255, 255, 255), (255, 128, 0)]);
+
+    let xs = vec![
+        "@TABLE\nneighborhood:Moore\n",
+        "@TABLE\nn_states:2\nneighborhood:Moore\n0,1,0,0,0,0,0,0,0\n",
+        "@TABLE\nn_states:2\nneighborhood:Moore\n0,1,0,0,0,0,0,0,0,2\n",
+        "@TABLE\nn_states:2\nneighborhood:Moore\n0,x,0,0,0,0,0,0,0,1\n",
+        "@TABLE\nn_states:2\nneighborhood:Moore\nvar a={0,1}\n0,0,0,0,0,0,0,0,0,a\n",
+        "@TABLE\nn_states:2\nneighborhood:triangular\n",
+        "@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:rotate8\n",
+        "@RULE Empty\n",
+    ];
+
+    for s in xs {
+        assert!(parse(s).is_err(), "{:?} should not parse", s);
+    }
+}
+
+#[test]
+fn test_fate() {
+    let table = parse(WIREWORLD).unwrap();
+    let states = |xs: &[u8]| xs.iter().map(|&x| State(x)).collect::<Vec<State>>();
+
+    assert_eq!(table.fate(State(1), &states(&[0, 3, 3, 0, 0, 0, 0, 0])), State(2));
+    assert_eq!(table.fate(State(2), &states(&[0, 3, 3, 0, 0, 0, 0, 0])), State(3));
+    assert_eq!(table.fate(State(3), &states(&[0, 0, 0, 1, 0, 0, 0, 0])), State(1));
+    assert_eq!(table.fate(State(3), &states(&[1, 0, 3, 0, 0, 0, 1, 0])), State(1));
+    assert_eq!(table.fate(State(3), &states(&[1, 1, 1, 0, 0, 0, 0, 0])), State(3));
+    assert_eq!(table.fate(State(0), &states(&[1, 1, 1, 0, 0, 0, 0, 0])), State(0));
+}
+
+#[test]
+fn test_symmetries() {
+    // A cell copies its north neighbour, turned every way.
+    let table = parse("\
+        @TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:rotate4\n\
+        var a={1,2}\nvar b={0,1,2}\n0,a,0,b,0,a\n0,a,b,a,b,1\n").unwrap();
+    let states = |xs: &[u8]| xs.iter().map(|&x| State(x)).collect::<Vec<State>>();
+
+    assert_eq!(table.fate(State(0), &states(&[2, 0, 1, 0])), State(2));
+    assert_eq!(table.fate(State(0), &states(&[0, 0, 1, 0])), State(1));
+    assert_eq!(table.fate(State(0), &states(&[0, 2, 0, 0])), State(2));
+    // Bound variables: both `a` must be the same state.
+    assert_eq!(table.fate(State(0), &states(&[1, 0, 2, 0])), State(1));
+    assert_eq!(table.fate(State(0), &states(&[2, 1, 2, 1])), State(1));
+    assert_eq!(table.fate(State(0), &states(&[2, 1, 2, 0])), State(0));
+    assert_eq!(table.fate(State(0), &states(&[2, 1, 1, 1])), State(0));
+
+    let keys = |xs: &[&str]| xs.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+    assert_eq!(arrangements(&keys(&["a", "b", "c"])).len(), 6);
+    assert_eq!(arrangements(&keys(&["a", "b", "a", "a"])).len(), 4);
+
+    match Neighbourhood::Hexagonal.symmetries("rotate6reflect") {
+        Some(Symmetry::Orderings(xs)) => assert_eq!(xs.len(), 12),
+        x => panic!("unexpected {:?}", x),
+    }
+}
+
+#[test]
+fn test_symmetries_before_neighbourhood() {
+    let table = parse("\
+        @TABLE\nn_states:2\nsymmetries:rotate4\nneighborhood:vonNeumann\n\
+        0,1,0,0,0,1\n").unwrap();
+    let states = |xs: &[u8]| xs.iter().map(|&x| State(x)).collect::<Vec<State>>();
+
+    assert_eq!(table.neighbourhood(), Neighbourhood::VonNeumann);
+    assert_eq!(table.fate(State(0), &states(&[0, 0, 1, 0])), State(1));
+}
+
+#[test]
+fn test_permute() {
+    // Totalistic Life, every transition written out once.
+    let mut s = String::from("@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:permute\n");
+
+    for cell in 0..2 {
+        for alive in 0..9 {
+            let next = (alive == 3 || (cell == 1 && alive == 2)) as u8;
+            let neighbours = (0..8).map(|i| if i < alive { "1" } else { "0" }).collect::<Vec<_>>();
+            s.push_str(&format!("{},{},{}\n", cell, neighbours.join(","), next));
+        }
+    }
+
+    let table = parse(&s).unwrap();
+    let states = |xs: &[u8]| xs.iter().map(|&x| State(x)).collect::<Vec<State>>();
+
+    // Each count of alive neighbours, in every arrangement: 2 * 2^8.
+    assert_eq!(table.transitions.len(), 512);
+    assert_eq!(table.fate(State(0), &states(&[0, 1, 0, 0, 1, 0, 0, 1])), State(1));
+    assert_eq!(table.fate(State(1), &states(&[0, 0, 0, 0, 1, 0, 0, 1])), State(1));
+    assert_eq!(table.fate(State(1), &states(&[1, 1, 0, 0, 1, 0, 0, 1])), State(0));
+}
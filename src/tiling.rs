@@ -0,0 +1,418 @@
+//! Grids of hexagons and triangles, with their own coordinates rather than
+//! a neighbourhood on a square grid. Edges are joined as a torus.
+//!
+//! Hexagonal grids are parallelograms, each row half a cell left of the one
+//! above, see `coord::Hex`. Triangular grids alternate triangles pointing up
+//! and down, see `coord::Tri`, so their sizes must be even to be joined.
+//!
+//! Triangles have 12 neighbours, counts above 8 are given to `Rule::new`.
+
+use itertools::Itertools;
+use ndarray::prelude::*;
+use std::fmt;
+
+use cell::Cell;
+use coord::{ Hex, Tri };
+use grid::{ Grid, GridItem };
+use rule::Rule;
+use topology::Topology;
+
+
+/// Offsets of the 6 neighbours of a hexagon, as (columns, rows).
+pub const HEX_NEIGHBOURS: [(isize, isize); 6] = [
+    (-1, -1), (0, -1), (-1, 0), (1, 0), (0, 1), (1, 1),
+];
+
+
+/// Fails if the rule is a non-totalistic one, which needs 8 neighbours.
+fn totalistic(rule: Rule) -> Result<Rule, String> {
+    if !rule.is_totalistic() {
+        return Err(format!("Hexagonal and triangular grids do not support the rule {}", rule));
+    }
+
+    Ok(rule)
+}
+
+fn count(cells: &[Cell]) -> usize {
+    cells.iter().filter(|x| x.is_alive()).count()
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+// Hexagons
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Eq)]
+pub struct Hexagons {
+    /// Stored row by row, `cells[[r, q]]`.
+    cells: Array2<Cell>,
+    size: (usize, usize),
+    gen: usize,
+    rule: Rule,
+}
+
+impl Hexagons {
+    /// Rows and columns of cells, indexed as `[[r, q]]`.
+    pub fn new(cells: Array2<Cell>, gen: usize) -> Self {
+        let size = cells.dim();
+
+        Hexagons {
+            cells: cells,
+            size: size,
+            gen: gen,
+            rule: Rule::default(),
+        }
+    }
+
+    /// Evolves with the given rule instead of Conway's.
+    ///
+    /// Panics if the rule is non-totalistic, see `try_with_rule`.
+    pub fn with_rule(self, rule: Rule) -> Self {
+        self.try_with_rule(rule).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Evolves with the given rule instead of Conway's, failing if it is
+    /// non-totalistic.
+    pub fn try_with_rule(mut self, rule: Rule) -> Result<Self, String> {
+        self.rule = totalistic(rule)?;
+        Ok(self)
+    }
+
+    pub fn rectangle(rows: usize, cols: usize) -> Self {
+        Hexagons::new(Array2::from_elem((rows, cols), Cell::Unborn), 1)
+    }
+
+    pub fn cells(&self) -> &Array2<Cell> {
+        &self.cells
+    }
+
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+
+    pub fn regenerate<T: Into<Hex>>(&mut self, coord: T) {
+        let Hex(q, r) = coord.into();
+        self.cells[[r, q]] = Cell::Alive;
+    }
+}
+
+
+impl Grid for Hexagons {
+    type Cell = Cell;
+    type Coord = Hex;
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn item(&self, coord: Hex) -> Cell {
+        self.cells[[coord.r(), coord.q()]]
+    }
+
+    fn item_neighbours(&self, coord: Hex) -> Vec<Cell> {
+        let (r, q) = (coord.r(), coord.q());
+
+        HEX_NEIGHBOURS.iter()
+            .filter_map(|&(dq, dr)| Topology::Torus.neighbour((r, q), (dr, dq), self.size))
+            .map(|(r, q)| self.cells[[r, q]])
+            .collect()
+    }
+
+    fn item_fate(&self, coord: Hex) -> Cell {
+        self.rule.fate(self.item(coord), count(&self.item_neighbours(coord)))
+    }
+
+    fn evolve(&self) -> Self {
+        let cells = Array2::from_shape_fn(self.size, |(r, q)| self.item_fate(Hex(q, r)));
+
+        Hexagons::new(cells, self.gen + 1).with_rule(self.rule)
+    }
+}
+
+
+impl IntoIterator for Hexagons {
+    type Item = (Hex, Cell);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.indexed_iter()
+            .map(|((r, q), &x)| (Hex(q, r), x))
+            .collect::<Vec<(Hex, Cell)>>()
+            .into_iter()
+    }
+}
+
+
+impl PartialEq for Hexagons {
+    fn eq(&self, other: &Hexagons) -> bool {
+        self.cells == other.cells
+    }
+}
+
+
+/// Rows shifted half a cell left of the one above.
+impl fmt::Display for Hexagons {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = self.size.0;
+        let res = self.cells.outer_iter()
+            .enumerate()
+            .map(|(r, xs)| format!("{}{}\n", " ".repeat(rows - 1 - r), xs.iter().join(" ")))
+            .collect::<String>();
+
+        write!(f, "{}", res)
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+// Triangles
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Eq)]
+pub struct Triangles {
+    cells: Array2<Cell>,
+    size: (usize, usize),
+    gen: usize,
+    rule: Rule,
+    edges: bool,
+}
+
+impl Triangles {
+    /// Panics if there's an odd number of rows or columns, which can't be
+    /// joined as a torus.
+    pub fn new(cells: Array2<Cell>, gen: usize) -> Self {
+        let size = cells.dim();
+
+        if size.0 % 2 != 0 || size.1 % 2 != 0 {
+            panic!("Triangular grid of {:?} can't be joined, sizes must be even", size);
+        }
+
+        Triangles {
+            cells: cells,
+            size: size,
+            gen: gen,
+            rule: Rule::default(),
+            edges: false,
+        }
+    }
+
+    /// Evolves with the given rule instead of Conway's.
+    ///
+    /// Panics if the rule is non-totalistic, see `try_with_rule`.
+    pub fn with_rule(self, rule: Rule) -> Self {
+        self.try_with_rule(rule).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Evolves with the given rule instead of Conway's, failing if it is
+    /// non-totalistic.
+    pub fn try_with_rule(mut self, rule: Rule) -> Result<Self, String> {
+        self.rule = totalistic(rule)?;
+        Ok(self)
+    }
+
+    /// Counts the 3 triangles sharing an edge with each cell, instead of the
+    /// 12 sharing a corner.
+    pub fn with_edge_neighbours(mut self) -> Self {
+        self.edges = true;
+        self
+    }
+
+    pub fn rectangle(rows: usize, cols: usize) -> Self {
+        Triangles::new(Array2::from_elem((rows, cols), Cell::Unborn), 1)
+    }
+
+    pub fn cells(&self) -> &Array2<Cell> {
+        &self.cells
+    }
+
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+
+    pub fn regenerate<T: Into<Tri>>(&mut self, coord: T) {
+        let (x, y) = coord.into().into();
+        self.cells[[x, y]] = Cell::Alive;
+    }
+
+    /// The coordinates of the neighbours of a cell, left to right and top
+    /// to bottom.
+    pub fn neighbour_coords(&self, coord: Tri) -> Vec<Tri> {
+        let up = coord.points_up();
+        let offsets: Vec<(isize, isize)> = match (self.edges, up) {
+            (true, true) => vec![(0, -1), (0, 1), (1, 0)],
+            (true, false) => vec![(-1, 0), (0, -1), (0, 1)],
+            (false, _) => {
+                // 3 cells on the side of the point, 5 on the side of the base.
+                let (above, below) = if up { (1, 2) } else { (2, 1) };
+
+                (-above..above + 1).map(|y| (-1, y))
+                    .chain((-2..3).filter(|&y| y != 0).map(|y| (0, y)))
+                    .chain((-below..below + 1).map(|y| (1, y)))
+                    .collect()
+            }
+        };
+
+        offsets.into_iter()
+            .filter_map(|offset| Topology::Torus.neighbour(coord.into(), offset, self.size))
+            .map(Tri::from)
+            .collect()
+    }
+}
+
+
+impl Grid for Triangles {
+    type Cell = Cell;
+    type Coord = Tri;
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn item(&self, coord: Tri) -> Cell {
+        self.cells[[coord.x(), coord.y()]]
+    }
+
+    fn item_neighbours(&self, coord: Tri) -> Vec<Cell> {
+        self.neighbour_coords(coord)
+            .into_iter()
+            .map(|x| self.item(x))
+            .collect()
+    }
+
+    fn item_fate(&self, coord: Tri) -> Cell {
+        self.rule.fate(self.item(coord), count(&self.item_neighbours(coord)))
+    }
+
+    fn evolve(&self) -> Self {
+        let cells = Array2::from_shape_fn(self.size, |x| self.item_fate(Tri::from(x)));
+
+        Triangles {
+            edges: self.edges,
+            .. Triangles::new(cells, self.gen + 1).with_rule(self.rule)
+        }
+    }
+}
+
+
+impl IntoIterator for Triangles {
+    type Item = (Tri, Cell);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.indexed_iter()
+            .map(|(x, &cell)| (Tri::from(x), cell))
+            .collect::<Vec<(Tri, Cell)>>()
+            .into_iter()
+    }
+}
+
+
+impl PartialEq for Triangles {
+    fn eq(&self, other: &Triangles) -> bool {
+        self.cells == other.cells && self.edges == other.edges
+    }
+}
+
+
+/// Empty cells as `^` or `v`, the way they point.
+impl fmt::Display for Triangles {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let res = self.cells.indexed_iter()
+            .map(|(x, cell)| match *cell {
+                Cell::Unborn if Tri::from(x).points_up() => "^".to_string(),
+                Cell::Unborn => "v".to_string(),
+                _ => format!("{}", cell),
+            })
+            .chunks(self.size.1)
+            .into_iter()
+            .map(|xs| format!("{}\n", xs.collect::<String>()))
+            .collect::<String>();
+
+        write!(f, "{}", res)
+    }
+}
+
+
+#[test]
+fn test_hexagons() {
+    use community::Community;
+    use neighbourhood::Neighbourhood;
+    use pattern::{ Layout, Random };
+
+    // The same as the hexagonal neighbourhood on a square grid, stored the
+    // same way.
+    let rule: Rule = "B2/S34".parse().unwrap();
+    let mut community = Community::rectangle(12, 16)
+        .with_rule(rule)
+        .with_neighbourhood(Neighbourhood::Hexagonal);
+    community.insert(Layout::new((0, 0), Random(12, 16).with_seed(7)));
+
+    let cells = Array2::from_shape_fn((12, 16), |(x, y)| community.item((x, y).into()));
+    let mut hexagons = Hexagons::new(cells, 1).with_rule(rule);
+
+    for _ in 0..8 {
+        community = community.evolve();
+        hexagons = hexagons.evolve();
+    }
+
+    let expected = Array2::from_shape_fn((12, 16), |(x, y)| community.item((x, y).into()));
+
+    assert_eq!(hexagons.cells(), &expected);
+    assert_eq!(hexagons.item_neighbours(Hex(0, 0)).len(), 6);
+    assert!(Hexagons::rectangle(3, 3).try_with_rule("B2-a/S12".parse().unwrap()).is_err());
+
+    let mut grid = Hexagons::rectangle(3, 3);
+    grid.regenerate(Hex(1, 1));
+
+    assert_eq!(format!("{}", grid), "  _ _ _\n _ # _\n_ _ _\n");
+    assert_eq!(grid.into_iter().nth(5), Some((Hex(2, 1), Cell::Unborn)));
+}
+
+#[test]
+fn test_triangles() {
+    let grid = Triangles::rectangle(4, 6);
+
+    // Every cell is a neighbour of its neighbours.
+    for (x, y) in iproduct!(0..4, 0..6) {
+        let coord = Tri(x, y);
+        let xs = grid.neighbour_coords(coord);
+
+        assert_eq!(xs.len(), 12, "{:?}", coord);
+        assert!(!xs.contains(&coord));
+        assert!(xs.iter().all(|&x| grid.neighbour_coords(x).contains(&coord)), "{:?}", coord);
+    }
+
+    assert!(grid.clone().try_with_rule("B2-a/S12".parse().unwrap()).is_err());
+
+    let edges = grid.clone().with_edge_neighbours();
+
+    assert_eq!(edges.neighbour_coords(Tri(0, 0)), vec![Tri(0, 5), Tri(0, 1), Tri(1, 0)]);
+    assert_eq!(edges.neighbour_coords(Tri(1, 0)), vec![Tri(0, 0), Tri(1, 5), Tri(1, 1)]);
+
+    // A cell gives birth across its 3 edges and dies.
+    let mut grid = Triangles::rectangle(4, 6)
+        .with_rule("B1/S".parse().unwrap())
+        .with_edge_neighbours();
+    grid.regenerate(Tri(2, 2));
+    grid = grid.evolve();
+
+    assert_eq!(format!("{}", grid), "^v^v^v\nv^v^v^\n^#.#^v\nv^#^v^\n");
+    assert_eq!(grid.generation(), 2);
+
+    // Cells survive with all of their 12 neighbours alive.
+    let mut grid = Triangles::rectangle(4, 6).with_rule(Rule::new(&[], &[12]));
+
+    for (x, y) in iproduct!(0..4, 0..6) {
+        grid.regenerate(Tri(x, y));
+    }
+
+    assert_eq!(grid.evolve().cells().iter().filter(|x| x.is_alive()).count(), 24);
+}
@@ -0,0 +1,118 @@
+//! How the edges of a bounded grid are joined.
+//!
+//! Crossing an edge either leads nowhere, the cells beyond it being always
+//! dead, or back in from the opposite edge. A twisted join comes back
+//! mirrored, as on a Klein bottle, and a sheared one comes back shifted.
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Topology {
+    /// No edges are joined.
+    Plane,
+    /// Top joined to bottom and left to right.
+    Torus,
+    /// Left joined to right, top and bottom are bounded.
+    Cylinder,
+    /// Left joined to right, top joined to bottom with a twist: columns are
+    /// mirrored when crossing it.
+    KleinBottle,
+    /// The real projective plane: both pairs of edges are joined with a
+    /// twist.
+    CrossSurface,
+    /// A torus shifting columns by the given amount when crossing the bottom
+    /// edge, and back when crossing the top one.
+    ShearedTorus(isize),
+}
+
+impl Topology {
+    /// The cell `offset` rows and columns away from `coord` on a grid of
+    /// `size`, `None` if it's beyond an edge.
+    pub fn neighbour(&self, coord: (usize, usize), offset: (isize, isize), size: (usize, usize))
+        -> Option<(usize, usize)> {
+        let (n, m) = (size.0 as isize, size.1 as isize);
+        let (mut x, mut y) = (coord.0 as isize + offset.0, coord.1 as isize + offset.1);
+        let across = |x: isize, n: isize| x < 0 || x >= n;
+
+        match *self {
+            Topology::Plane => {
+                if across(x, n) || across(y, m) {
+                    return None;
+                }
+            }
+            Topology::Torus => {}
+            Topology::Cylinder => {
+                if across(x, n) {
+                    return None;
+                }
+            }
+            Topology::KleinBottle => {
+                if across(x, n) {
+                    y = m - 1 - y;
+                }
+            }
+            Topology::CrossSurface => {
+                let (mirror_x, mirror_y) = (across(y, m), across(x, n));
+
+                if mirror_x {
+                    x = n - 1 - x;
+                }
+
+                if mirror_y {
+                    y = m - 1 - y;
+                }
+            }
+            Topology::ShearedTorus(shift) => {
+                if x < 0 {
+                    y -= shift;
+                } else if x >= n {
+                    y += shift;
+                }
+            }
+        }
+
+        Some((wrap(x, n), wrap(y, m)))
+    }
+
+    /// The cells at each offset from the cell, `None` for the ones beyond an
+    /// edge.
+    pub fn neighbours(&self, coord: (usize, usize), offsets: &[(isize, isize)], size: (usize, usize))
+        -> Vec<Option<(usize, usize)>> {
+        offsets.iter()
+            .map(|&offset| self.neighbour(coord, offset, size))
+            .collect()
+    }
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Topology::Torus
+    }
+}
+
+
+fn wrap(x: isize, n: isize) -> usize {
+    (((x % n) + n) % n) as usize
+}
+
+
+#[test]
+fn test_neighbour() {
+    let size = (4, 6);
+    let xs = vec![
+        (Topology::Plane, (1, 1), (1, 1), Some((2, 2))),
+        (Topology::Plane, (0, 2), (-1, 0), None),
+        (Topology::Torus, (0, 0), (-1, -1), Some((3, 5))),
+        (Topology::Cylinder, (2, 5), (0, 1), Some((2, 0))),
+        (Topology::Cylinder, (3, 2), (1, 0), None),
+        (Topology::KleinBottle, (3, 1), (1, 0), Some((0, 4))),
+        (Topology::KleinBottle, (1, 5), (0, 1), Some((1, 0))),
+        (Topology::CrossSurface, (1, 5), (0, 1), Some((2, 0))),
+        (Topology::CrossSurface, (0, 0), (-1, 0), Some((3, 5))),
+        (Topology::ShearedTorus(2), (3, 1), (1, 0), Some((0, 3))),
+        (Topology::ShearedTorus(2), (0, 1), (-1, 0), Some((3, 5))),
+    ];
+
+    for (topology, coord, offset, expected) in xs {
+        assert_eq!(topology.neighbour(coord, offset, size), expected, "{:?}", topology);
+    }
+}
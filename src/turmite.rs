@@ -0,0 +1,431 @@
+//! Turmites: ants walking over a grid of colors, turning and repainting as
+//! they go.
+//!
+//! A turmite reads the color under it, and depending on its own state writes
+//! a new color, turns, moves one cell forward and switches state. Rules are
+//! written the way Golly does:
+//!
+//! * Ants with a single state as a turn per color, `RL` being Langton's Ant:
+//!   `N` no turn, `R` right, `U` u-turn and `L` left. Each color is painted
+//!   over with the next one.
+//! * Any turmite as `{{{1, 2, 0}, {0, 8, 0}}}`: for each state, for each
+//!   color, the color written, the turn (1 none, 2 right, 4 u-turn, 8 left)
+//!   and the next state.
+
+use itertools::Itertools;
+use ndarray::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use cell::State;
+use coord::Dim2 as Coord;
+use grid::Grid;
+use topology::Topology;
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    /// The offset of one step forward, as (rows, columns).
+    pub fn offset(&self) -> (isize, isize) {
+        match *self {
+            Direction::North => (-1, 0),
+            Direction::East => (0, 1),
+            Direction::South => (1, 0),
+            Direction::West => (0, -1),
+        }
+    }
+
+    pub fn turn(&self, turn: Turn) -> Direction {
+        let xs = [Direction::North, Direction::East, Direction::South, Direction::West];
+        let i = xs.iter().position(|x| x == self).unwrap();
+
+        xs[(i + turn as usize) % 4]
+    }
+}
+
+
+/// Turns, as quarters clockwise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Turn {
+    None = 0,
+    Right = 1,
+    UTurn = 2,
+    Left = 3,
+}
+
+impl Turn {
+    /// The turn of Golly's notation, 1, 2, 4 or 8.
+    fn from_code(x: usize) -> Option<Turn> {
+        match x {
+            1 => Some(Turn::None),
+            2 => Some(Turn::Right),
+            4 => Some(Turn::UTurn),
+            8 => Some(Turn::Left),
+            _ => None,
+        }
+    }
+
+    fn code(&self) -> usize {
+        1 << *self as usize
+    }
+}
+
+
+/// What a turmite in a given state does on each color.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Transition {
+    pub color: u8,
+    pub turn: Turn,
+    pub state: usize,
+}
+
+
+/// The rule of a turmite, `transitions[state][color]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turmite {
+    transitions: Vec<Vec<Transition>>,
+}
+
+impl Turmite {
+    /// Langton's Ant.
+    pub fn langton() -> Self {
+        "RL".parse().unwrap()
+    }
+
+    pub fn states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn colors(&self) -> usize {
+        self.transitions[0].len()
+    }
+
+    pub fn transition(&self, state: usize, color: u8) -> Transition {
+        self.transitions[state][color as usize]
+    }
+}
+
+impl FromStr for Turmite {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Turmite, String> {
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let invalid = || format!("Invalid turmite {:?}", s);
+
+        if !s.starts_with('{') {
+            let turns = s.chars()
+                .map(|c| match c.to_ascii_uppercase() {
+                    'N' => Ok(Turn::None),
+                    'R' => Ok(Turn::Right),
+                    'U' => Ok(Turn::UTurn),
+                    'L' => Ok(Turn::Left),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<Vec<Turn>, String>>()?;
+
+            if turns.len() < 2 || turns.len() > 256 {
+                return Err(invalid());
+            }
+
+            let n = turns.len();
+            let transitions = turns.into_iter()
+                .enumerate()
+                .map(|(i, turn)| Transition { color: ((i + 1) % n) as u8, turn: turn, state: 0 })
+                .collect();
+
+            return Ok(Turmite { transitions: vec![transitions] });
+        }
+
+        if !s.starts_with("{{{") || !s.ends_with("}}}") {
+            return Err(invalid());
+        }
+
+        let transitions = s[3..s.len() - 3]
+            .split("}},{{")
+            .map(|state| {
+                state.split("},{")
+                    .map(|x| {
+                        let xs: Vec<usize> = x.split(',')
+                            .map(|x| x.parse().map_err(|_| invalid()))
+                            .collect::<Result<_, _>>()?;
+
+                        match (xs.len(), xs.get(1).and_then(|&x| Turn::from_code(x))) {
+                            (3, Some(turn)) if xs[0] < 256 => {
+                                Ok(Transition { color: xs[0] as u8, turn: turn, state: xs[2] })
+                            }
+                            _ => Err(invalid()),
+                        }
+                    })
+                    .collect::<Result<Vec<Transition>, String>>()
+            })
+            .collect::<Result<Vec<Vec<Transition>>, String>>()?;
+
+        let (states, colors) = (transitions.len(), transitions[0].len());
+        let valid = transitions.iter().all(|xs| {
+            xs.len() == colors &&
+            xs.iter().all(|x| (x.color as usize) < colors && x.state < states)
+        });
+
+        if !valid || colors < 2 {
+            return Err(invalid());
+        }
+
+        Ok(Turmite { transitions: transitions })
+    }
+}
+
+impl fmt::Display for Turmite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let states = self.transitions.iter()
+            .map(|xs| {
+                xs.iter()
+                    .map(|x| format!("{{{}, {}, {}}}", x.color, x.turn.code(), x.state))
+                    .join(", ")
+            })
+            .map(|x| format!("{{{}}}", x))
+            .join(", ");
+
+        write!(f, "{{{}}}", states)
+    }
+}
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Ant {
+    pub position: (usize, usize),
+    pub direction: Direction,
+    pub state: usize,
+}
+
+impl Ant {
+    pub fn new(position: (usize, usize), direction: Direction) -> Self {
+        Ant {
+            position: position,
+            direction: direction,
+            state: 0,
+        }
+    }
+}
+
+
+/// Grid of colors with ants walking on it, edges joined as a torus.
+#[derive(Debug, Clone, Eq)]
+pub struct Turmites {
+    cells: Array2<State>,
+    size: (usize, usize),
+    gen: usize,
+    ants: Vec<Ant>,
+    turmite: Arc<Turmite>,
+}
+
+impl Turmites {
+    /// A grid of color 0 without ants.
+    pub fn new(turmite: Turmite, rows: usize, cols: usize) -> Self {
+        Turmites {
+            cells: Array2::from_elem((rows, cols), State(0)),
+            size: (rows, cols),
+            gen: 1,
+            ants: vec![],
+            turmite: Arc::new(turmite),
+        }
+    }
+
+    pub fn turmite(&self) -> &Turmite {
+        &self.turmite
+    }
+
+    /// Ants move in the order they were inserted.
+    pub fn ants(&self) -> &[Ant] {
+        &self.ants
+    }
+
+    pub fn insert(&mut self, ant: Ant) {
+        if ant.position.0 >= self.size.0 || ant.position.1 >= self.size.1 {
+            panic!("Ant at {:?} outside grid of {:?}", ant.position, self.size);
+        }
+
+        self.ants.push(ant);
+    }
+
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+}
+
+
+impl Grid for Turmites {
+    type Cell = State;
+    type Coord = Coord;
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn item(&self, coord: Coord) -> State {
+        let (x, y) = coord.into();
+        self.cells[[x, y]]
+    }
+
+    /// The 4 cells an ant can move to.
+    fn item_neighbours(&self, coord: Coord) -> Vec<State> {
+        let offsets = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+        Topology::Torus.neighbours(coord.into(), &offsets, self.size)
+            .into_iter()
+            .filter_map(|x| x.map(|(x, y)| self.cells[[x, y]]))
+            .collect()
+    }
+
+    /// The color written by the first ant on the cell, if any.
+    fn item_fate(&self, coord: Coord) -> State {
+        let (x, y) = coord.into();
+        let cell = self.cells[[x, y]];
+
+        self.ants.iter()
+            .find(|ant| ant.position == (x, y))
+            .map_or(cell, |ant| State(self.turmite.transition(ant.state, cell.0).color))
+    }
+
+    /// Moves every ant one step, each one seeing the colors written by the
+    /// ones before it.
+    fn evolve(&self) -> Self {
+        let mut cells = self.cells.clone();
+        let mut ants = self.ants.clone();
+
+        for ant in &mut ants {
+            let (x, y) = ant.position;
+            let transition = self.turmite.transition(ant.state, cells[[x, y]].0);
+
+            cells[[x, y]] = State(transition.color);
+            ant.direction = ant.direction.turn(transition.turn);
+            ant.state = transition.state;
+            ant.position = Topology::Torus
+                .neighbour(ant.position, ant.direction.offset(), self.size)
+                .unwrap();
+        }
+
+        Turmites {
+            cells: cells,
+            gen: self.gen + 1,
+            ants: ants,
+            turmite: self.turmite.clone(),
+            .. *self
+        }
+    }
+}
+
+
+impl IntoIterator for Turmites {
+    type Item = (Coord, State);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.iter()
+            .enumerate()
+            .map(|(i, &x)| (Coord::from_row_major(i, self.size.1), x))
+            .collect::<Vec<(Coord, State)>>()
+            .into_iter()
+    }
+}
+
+
+impl PartialEq for Turmites {
+    fn eq(&self, other: &Turmites) -> bool {
+        self.cells == other.cells && self.ants == other.ants
+    }
+}
+
+
+/// Colors as states, ants as arrows pointing where they head.
+impl fmt::Display for Turmites {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let res = self.cells.indexed_iter()
+            .map(|(coord, x)| {
+                match self.ants.iter().find(|ant| ant.position == coord) {
+                    Some(ant) => match ant.direction {
+                        Direction::North => "^".to_string(),
+                        Direction::East => ">".to_string(),
+                        Direction::South => "v".to_string(),
+                        Direction::West => "<".to_string(),
+                    },
+                    None => format!("{}", x),
+                }
+            })
+            .chunks(self.size.1)
+            .into_iter()
+            .map(|xs| format!("{}\n", xs.collect::<String>()))
+            .collect::<String>();
+
+        write!(f, "{}", res)
+    }
+}
+
+
+#[test]
+fn test_parse() {
+    let ant = Turmite::langton();
+
+    assert_eq!(ant.to_string(), "{{{1, 2, 0}, {0, 8, 0}}}");
+    assert_eq!("{{{1,2,0},{0,8,0}}}".parse(), Ok(ant));
+    assert_eq!("llrr".parse::<Turmite>().map(|x| x.colors()), Ok(4));
+
+    // Fibonacci spiral, with 2 states.
+    let fibonacci: Turmite = "{{{1, 8, 1}, {1, 8, 1}}, {{1, 2, 1}, {0, 1, 0}}}".parse().unwrap();
+
+    assert_eq!(fibonacci.states(), 2);
+    assert_eq!(fibonacci.transition(1, 1), Transition { color: 0, turn: Turn::None, state: 0 });
+    assert_eq!(fibonacci.to_string(), "{{{1, 8, 1}, {1, 8, 1}}, {{1, 2, 1}, {0, 1, 0}}}");
+
+    let xs = vec!["", "R", "RX", "{{{1, 2, 0}}}", "{{{1, 3, 0}, {0, 8, 0}}}",
+                  "{{{2, 2, 0}, {0, 8, 0}}}", "{{{1, 2, 1}, {0, 8, 0}}}",
+                  "{{{1, 2, 0}, {0, 8, 0}}, {{1, 2, 0}}}", "{{1, 2, 0}, {0, 8, 0}}"];
+
+    for s in xs {
+        assert!(s.parse::<Turmite>().is_err(), "{:?} should not parse", s);
+    }
+}
+
+#[test]
+fn test_langton() {
+    let mut grid = Turmites::new(Turmite::langton(), 5, 5);
+    grid.insert(Ant::new((2, 2), Direction::North));
+
+    // Turning right on every empty cell, the ant paints a square and comes
+    // back where it started.
+    for _ in 0..4 {
+        grid = grid.evolve();
+    }
+
+    assert_eq!(grid.ants(), &[Ant::new((2, 2), Direction::North)]);
+    assert_eq!(format!("{}", grid), ".....\n.....\n..^A.\n..AA.\n.....\n");
+    assert_eq!(grid.item_fate(Coord(2, 2)), State(0));
+
+    grid = grid.evolve();
+
+    assert_eq!(grid.ants(), &[Ant::new((2, 1), Direction::West)]);
+    assert_eq!(grid.item(Coord(2, 2)), State(0));
+    assert_eq!(grid.generation(), 6);
+}
+
+#[test]
+fn test_ants() {
+    let mut grid = Turmites::new(Turmite::langton(), 8, 8);
+    grid.insert(Ant::new((0, 0), Direction::North));
+    grid.insert(Ant::new((0, 0), Direction::South));
+
+    // The first ant paints the cell and turns right, the second one finds it
+    // painted, clears it and turns left: both head east.
+    grid = grid.evolve();
+
+    assert_eq!(grid.ants()[0], Ant::new((0, 1), Direction::East));
+    assert_eq!(grid.ants()[1], Ant::new((0, 1), Direction::East));
+    assert_eq!(grid.item(Coord(0, 0)), State(0));
+}
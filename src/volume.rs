@@ -0,0 +1,287 @@
+//! 3D grid using `ndarray::Array3<Cell>`, edges joined as a 3D torus.
+//!
+//! Cells have 26 neighbours and evolve with Bays' rules, written `ElEuFlFu`:
+//! an alive cell survives with `El` to `Eu` alive neighbours, an empty one
+//! is born with `Fl` to `Fu`. `4555` and `5766` are the best known.
+
+use itertools::Itertools;
+use ndarray::prelude::*;
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+
+use cell::Cell;
+use coord::Dim3 as Coord;
+use grid::{ Grid, GridItem };
+use pattern::{ self, Random };
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Bays {
+    survival: (usize, usize),
+    birth: (usize, usize),
+}
+
+impl Bays {
+    pub fn new(survival: (usize, usize), birth: (usize, usize)) -> Self {
+        Bays {
+            survival: survival,
+            birth: birth,
+        }
+    }
+
+    /// The next state of the given cell with `count` alive neighbours.
+    pub fn fate(&self, cell: Cell, count: usize) -> Cell {
+        let within = |(min, max): (usize, usize)| count >= min && count <= max;
+
+        match cell.is_alive() {
+            true if within(self.survival) => cell.keep(),
+            true                          => cell.kill(),
+            false if within(self.birth)   => cell.revive(),
+            false                         => cell.rot(),
+        }
+    }
+}
+
+impl Default for Bays {
+    fn default() -> Self {
+        Bays::new((4, 5), (5, 5))
+    }
+}
+
+/// Four digits, or four numbers separated by `/` when some go over 9.
+impl FromStr for Bays {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Bays, String> {
+        let s = s.trim();
+        let invalid = || format!("Invalid rulestring {:?}", s);
+
+        let xs: Vec<usize> = if s.contains('/') {
+            s.split('/')
+                .map(|x| x.trim().parse().map_err(|_| invalid()))
+                .collect::<Result<_, _>>()?
+        } else {
+            s.chars()
+                .map(|c| c.to_digit(10).map(|x| x as usize).ok_or_else(invalid))
+                .collect::<Result<_, _>>()?
+        };
+
+        if xs.len() != 4 || xs.iter().any(|&x| x > 26) || xs[0] > xs[1] || xs[2] > xs[3] {
+            return Err(invalid());
+        }
+
+        Ok(Bays::new((xs[0], xs[1]), (xs[2], xs[3])))
+    }
+}
+
+impl fmt::Display for Bays {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let xs = [self.survival.0, self.survival.1, self.birth.0, self.birth.1];
+
+        if xs.iter().all(|&x| x < 10) {
+            write!(f, "{}", xs.iter().join(""))
+        } else {
+            write!(f, "{}", xs.iter().join("/"))
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Eq)]
+pub struct Volume {
+    cells: Array3<Cell>,
+    size: (usize, usize, usize),
+    gen: usize,
+    rule: Bays,
+}
+
+impl Volume {
+    pub fn new(cells: Array3<Cell>, gen: usize) -> Self {
+        let size = cells.dim();
+
+        Volume {
+            cells: cells,
+            size: size,
+            gen: gen,
+            rule: Bays::default(),
+        }
+    }
+
+    /// Evolves with the given rule instead of `4555`.
+    pub fn with_rule(mut self, rule: Bays) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    pub fn empty(n: usize) -> Self {
+        Volume::cuboid(n, n, n)
+    }
+
+    pub fn cuboid(rows: usize, cols: usize, depth: usize) -> Self {
+        Volume::new(Array3::from_elem((rows, cols, depth), Cell::Unborn), 1)
+    }
+
+    /// A random cube, the same for a given seed on every platform.
+    pub fn seeded(n: usize, density: f64, seed: u64) -> Self {
+        Volume::random_with(n, density, &mut pattern::seeded(seed))
+    }
+
+    /// A random cube drawn from the given generator, slice by slice.
+    pub fn random_with<R: Rng>(n: usize, density: f64, rng: &mut R) -> Self {
+        let mut grid = Volume::empty(n);
+
+        for z in 0..n {
+            let slice = Random(n, n).with_density(density).fill(rng);
+            grid.cells.subview_mut(Axis(2), z).assign(&slice);
+        }
+
+        grid
+    }
+
+    /// Number of slices.
+    pub fn depth(&self) -> usize {
+        self.size.2
+    }
+
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+
+    /// The cells of the given slice, as rows and columns.
+    pub fn slice(&self, z: usize) -> Array2<Cell> {
+        self.cells.subview(Axis(2), z).to_owned()
+    }
+
+    pub fn regenerate(&mut self, coord: (usize, usize, usize)) {
+        let (x, y, z) = coord;
+        self.cells[[x, y, z]] = Cell::Alive;
+    }
+}
+
+
+impl Grid for Volume {
+    type Cell = Cell;
+    type Coord = Coord;
+
+    /// Rows and columns of each slice, see `depth`.
+    fn size(&self) -> (usize, usize) {
+        (self.size.0, self.size.1)
+    }
+
+    fn item(&self, coord: Coord) -> Cell {
+        let (x, y, z) = coord.into();
+        self.cells[[x, y, z]]
+    }
+
+    fn item_neighbours(&self, coord: Coord) -> Vec<Cell> {
+        let (n, m, l) = self.size;
+        let wrap = |x: usize, d: isize, n: usize| ((x + n) as isize + d) as usize % n;
+
+        iproduct!(-1..2, -1..2, -1..2)
+            .filter(|&x| x != (0, 0, 0))
+            .map(|(dx, dy, dz)| {
+                self.cells[[wrap(coord.0, dx, n), wrap(coord.1, dy, m), wrap(coord.2, dz, l)]]
+            })
+            .collect()
+    }
+
+    fn item_fate(&self, coord: Coord) -> Cell {
+        let count = self.item_neighbours(coord).iter().filter(|x| x.is_alive()).count();
+        self.rule.fate(self.item(coord), count)
+    }
+
+    fn evolve(&self) -> Self {
+        let cells = Array3::from_shape_fn(self.size, |(x, y, z)| self.item_fate(Coord(x, y, z)));
+
+        Volume::new(cells, self.gen + 1).with_rule(self.rule)
+    }
+}
+
+
+impl IntoIterator for Volume {
+    type Item = (Coord, Cell);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.indexed_iter()
+            .map(|(x, &cell)| (Coord::from(x), cell))
+            .collect::<Vec<(Coord, Cell)>>()
+            .into_iter()
+    }
+}
+
+
+impl PartialEq for Volume {
+    fn eq(&self, other: &Volume) -> bool {
+        self.cells == other.cells
+    }
+}
+
+
+/// Slice by slice, separated by blank lines.
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let res = (0..self.depth())
+            .map(|z| {
+                self.slice(z)
+                    .outer_iter()
+                    .map(|xs| format!("{}\n", xs.iter().map(|x| format!("{}", x)).join("")))
+                    .join("")
+            })
+            .join("\n");
+
+        write!(f, "{}", res)
+    }
+}
+
+
+#[test]
+fn test_parse() {
+    assert_eq!("4555".parse(), Ok(Bays::default()));
+    assert_eq!("5766".parse(), Ok(Bays::new((5, 7), (6, 6))));
+    assert_eq!("10/21/10/21".parse(), Ok(Bays::new((10, 21), (10, 21))));
+    assert_eq!(Bays::new((10, 21), (10, 21)).to_string(), "10/21/10/21");
+    assert_eq!(Bays::default().to_string(), "4555");
+
+    for s in vec!["", "455", "45555", "5455", "4a55", "4/5/5/27", "B3/S23"] {
+        assert!(s.parse::<Bays>().is_err(), "{:?} should not parse", s);
+    }
+}
+
+#[test]
+fn test_still() {
+    let rule: Bays = "5766".parse().unwrap();
+    let mut cube = Volume::empty(6).with_rule(rule);
+    let mut bent = Volume::empty(6);
+
+    // A 2 x 2 x 2 cube under 5766, and the same cube without two cells
+    // under 4555, across the edges of the torus.
+    for (x, y, z) in iproduct!(0..2, 0..2, 0..2) {
+        let coord = ((x + 5) % 6, (y + 5) % 6, (z + 5) % 6);
+        cube.regenerate(coord);
+
+        if (x, y, z) != (1, 1, 0) && (x, y, z) != (1, 1, 1) {
+            bent.regenerate(coord);
+        }
+    }
+
+    assert_eq!(cube.evolve(), cube);
+    assert_eq!(bent.evolve(), bent);
+    assert_eq!(cube.item_neighbours(Coord(0, 0, 0)).len(), 26);
+    assert!(cube.clone().with_rule(Bays::default()).evolve() != cube);
+}
+
+#[test]
+fn test_display() {
+    let mut grid = Volume::cuboid(2, 3, 2);
+    grid.regenerate((0, 1, 0));
+    grid.regenerate((1, 2, 1));
+
+    let alive = |x: String| x.replace('_', ".");
+
+    assert_eq!(grid.size(), (2, 3));
+    assert_eq!(alive(format!("{}", grid)), ".#.\n...\n\n...\n..#\n");
+    assert_eq!(grid.into_iter().nth(3), Some((Coord(0, 1, 1), Cell::Unborn)));
+    assert_eq!(Volume::seeded(8, 0.5, 1), Volume::seeded(8, 0.5, 1));
+}
@@ -1,4 +1,40 @@
-use grid::Grid;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{ Hash, Hasher };
+
+use grid::{ Grid, GridItem };
+use rule::Rule;
+
+
+/// How the universe settled down. Generations are counted by the world,
+/// starting at 1 with the grid it was given.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stabilization {
+    /// Every cell is dead since the given generation.
+    Extinct { since: usize },
+    /// Nothing changes since the given generation.
+    Still { since: usize },
+    /// The same states repeat every `period` generations since the given one.
+    Oscillating { period: usize, since: usize },
+}
+
+impl Stabilization {
+    pub fn period(&self) -> usize {
+        match *self {
+            Stabilization::Oscillating { period, .. } => period,
+            _ => 1,
+        }
+    }
+
+    /// The first generation of the cycle.
+    pub fn since(&self) -> usize {
+        match *self {
+            Stabilization::Extinct { since } => since,
+            Stabilization::Still { since } => since,
+            Stabilization::Oscillating { since, .. } => since,
+        }
+    }
+}
 
 
 /// The world of Conway.
@@ -6,6 +42,14 @@ use grid::Grid;
 pub struct World<T> {
     grid: T,
     size: (usize, usize),
+    gen: usize,
+    detect: bool,
+    stop: bool,
+    /// The grid given to the world, evolved again to confirm repeats.
+    start: T,
+    /// The generations seen so far, by hash of their grid.
+    history: HashMap<u64, Vec<usize>>,
+    stabilization: Option<Stabilization>,
 }
 
 impl<T: Grid + Into<T>> World<T> {
@@ -14,11 +58,26 @@ impl<T: Grid + Into<T>> World<T> {
         let size = grid.size();
 
         World {
+            start: grid.clone(),
             grid: grid,
-            size: (size, size),
+            size: size,
+            gen: 1,
+            detect: false,
+            stop: false,
+            history: HashMap::new(),
+            stabilization: None,
         }
     }
 
+    /// Remembers a hash of every state until the universe repeats itself.
+    /// When `stop` is set, iteration ends once the whole cycle has been
+    /// yielded.
+    pub fn with_cycle_detection(mut self, stop: bool) -> Self {
+        self.detect = true;
+        self.stop = stop;
+        self
+    }
+
     pub fn size(&self) -> (usize, usize) {
         self.size
     }
@@ -26,20 +85,191 @@ impl<T: Grid + Into<T>> World<T> {
     pub fn grid(&self) -> &T {
         &(self.grid)
     }
+
+    /// The generation of the next grid yielded.
+    pub fn generation(&self) -> usize {
+        self.gen
+    }
+
+    /// How the universe settled down, if cycle detection is on and it did.
+    pub fn stabilization(&self) -> Option<Stabilization> {
+        self.stabilization
+    }
+}
+
+impl<T, C> World<T>
+    where T: Grid + Into<T> + IntoIterator<Item = (C, <T as Grid>::Cell)>,
+          C: Hash + PartialEq {
+    /// Records the hash of the current grid, detecting the first repeat.
+    /// Nothing is recorded any more once it's found.
+    fn inspect(&mut self) {
+        if self.stabilization.is_some() {
+            return;
+        }
+
+        let gen = self.gen;
+        let hash = World::digest(&self.grid);
+
+        // A matching hash is confirmed against the grid of that generation,
+        // evolved again from the start. Only repeats and collisions do so.
+        let candidates = self.history.get(&hash).cloned().unwrap_or_default();
+        let seen = candidates.into_iter().find(|&x| World::same(&self.replay(x), &self.grid));
+
+        let seen = match seen {
+            Some(x) => x,
+            None => {
+                self.history.entry(hash).or_insert_with(Vec::new).push(gen);
+                return;
+            }
+        };
+
+        let alive = self.grid.clone().into_iter().any(|(_, cell)| cell.is_alive());
+
+        self.history.clear();
+        self.stabilization = Some(match gen - seen {
+            _ if !alive => Stabilization::Extinct { since: seen },
+            1 => Stabilization::Still { since: seen },
+            period => Stabilization::Oscillating { period: period, since: seen },
+        });
+    }
+
+    /// The grid of the given generation.
+    fn replay(&self, gen: usize) -> T {
+        (1..gen).fold(self.start.clone(), |grid, _| grid.evolve())
+    }
+
+    fn digest(grid: &T) -> u64 {
+        let rule = grid.rule();
+        let mut hasher = DefaultHasher::new();
+
+        for (coord, cell) in grid.clone() {
+            coord.hash(&mut hasher);
+            state(rule, cell).hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+
+    fn same(a: &T, b: &T) -> bool {
+        let rule = a.rule();
+
+        a.clone().into_iter().zip(b.clone()).all(|((x, c), (y, d))| {
+            x == y && state(rule, c) == state(rule, d)
+        })
+    }
+}
+
+/// A cell as it matters to tell grids apart: dead cells only differ while
+/// they can't be born again.
+fn state<X: GridItem>(rule: Rule, cell: X) -> (bool, Option<usize>) {
+    (cell.is_alive(), cell.age().filter(|&x| rule.is_refractory(x)))
 }
 
 
-impl<T: Grid + Into<T>> Iterator for World<T> {
+impl<T, C> Iterator for World<T>
+    where T: Grid + Into<T> + IntoIterator<Item = (C, <T as Grid>::Cell)>,
+          C: Hash + PartialEq {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.detect {
+            self.inspect();
+
+            if self.stop && self.stabilization.is_some() {
+                return None;
+            }
+        }
+
         let old = self.grid.clone();
         let new = self.grid.evolve();
 
-        // if old == new { return None; }
-
         self.grid = new;
+        self.gen += 1;
 
         Some(old)
     }
 }
+
+
+#[test]
+fn test_still() {
+    use community::Community;
+    use pattern::{ Layout, Loaf };
+
+    let mut grid = Community::empty(8);
+    grid.insert(Layout::new((2, 2), Loaf));
+
+    let mut world = World::new(grid).with_cycle_detection(true);
+
+    assert_eq!(world.by_ref().count(), 1);
+    assert_eq!(world.stabilization(), Some(Stabilization::Still { since: 1 }));
+}
+
+#[test]
+fn test_oscillating() {
+    use community::Community;
+    use pattern::{ Blinker, Layout };
+
+    let mut grid = Community::empty(8);
+    grid.insert(Layout::new((2, 2), Blinker));
+
+    let mut world = World::new(grid).with_cycle_detection(true);
+    let stabilization = Stabilization::Oscillating { period: 2, since: 1 };
+
+    assert_eq!(world.by_ref().count(), 2);
+    assert_eq!(world.stabilization(), Some(stabilization));
+    assert_eq!(stabilization.period(), 2);
+}
+
+#[test]
+fn test_extinct() {
+    use population::Population;
+
+    let mut grid = Population::empty(8);
+    grid.regenerate((3, 3));
+    grid.regenerate((3, 4));
+
+    let mut world = World::new(grid).with_cycle_detection(false);
+
+    assert_eq!(world.by_ref().take(10).count(), 10);
+    assert_eq!(world.stabilization(), Some(Stabilization::Extinct { since: 2 }));
+    assert_eq!(world.generation(), 11);
+
+    // Nothing more is recorded once the universe settled down.
+    assert!(world.history.is_empty());
+}
+
+#[test]
+fn test_glider_torus() {
+    use community::Community;
+    use pattern::{ Glider, Layout };
+
+    // A glider crosses an 8 x 8 torus diagonally in 32 generations.
+    let mut grid = Community::empty(8);
+    grid.insert(Layout::new((0, 0), Glider::BottomRight));
+
+    let mut world = World::new(grid).with_cycle_detection(true);
+
+    assert_eq!(world.by_ref().count(), 32);
+    assert_eq!(world.stabilization(), Some(Stabilization::Oscillating { period: 32, since: 1 }));
+}
+
+#[test]
+fn test_hash_collision() {
+    use community::Community;
+    use pattern::{ Blinker, Layout };
+
+    let mut grid = Community::empty(8);
+    grid.insert(Layout::new((2, 2), Blinker));
+
+    // The second phase recorded under the hash of the first is not a repeat.
+    let mut world = World::new(grid.clone()).with_cycle_detection(false);
+    world.history.insert(World::digest(&grid.clone().evolve()), vec![1]);
+
+    assert_eq!(world.by_ref().take(2).count(), 2);
+    assert_eq!(world.stabilization(), None);
+    assert_eq!(world.history.values().map(|x| x.len()).sum::<usize>(), 3);
+
+    assert!(world.next().is_some());
+    assert_eq!(world.stabilization(), Some(Stabilization::Oscillating { period: 2, since: 1 }));
+}